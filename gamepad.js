@@ -0,0 +1,30 @@
+// Lecture de la première manette via l'API Gamepad du navigateur (voir src/gamepad.rs)
+function first_gamepad() {
+    var pads = navigator.getGamepads ? navigator.getGamepads() : [];
+    for (var i = 0; i < pads.length; i++) {
+        if (pads[i] && pads[i].connected) {
+            return pads[i];
+        }
+    }
+    return null;
+}
+
+gamepad_register_js_plugin = function (importObject) {
+    importObject.env.gamepad_connected = function () {
+        return +(first_gamepad() != null);
+    }
+    importObject.env.gamepad_axis = function (axis) {
+        var pad = first_gamepad();
+        return pad && axis < pad.axes.length ? pad.axes[axis] : 0.0;
+    }
+    importObject.env.gamepad_button = function (button) {
+        var pad = first_gamepad();
+        return +(pad != null && button < pad.buttons.length && pad.buttons[button].pressed);
+    }
+}
+
+miniquad_add_plugin({
+    register_plugin: gamepad_register_js_plugin,
+    name: "gamepad",
+    version: "0.1.0"
+});
@@ -0,0 +1,138 @@
+/* Structure Achievements (succès débloqués, sauvegardés avec quad_storage) */
+
+use crate::events::*;
+use crate::text_display::draw_centered;
+use macroquad::prelude::*;
+
+// clé de sauvegarde : identifiants des succès débloqués, séparés par des virgules
+const STORAGE_KEY: &str = "achievements";
+// durée d'affichage de l'annonce d'un succès
+const TOAST_DURATION: f32 = 3.0;
+// ennemis à détruire dans une même partie pour "Centurion"
+const CENTURION_KILLS: u32 = 100;
+
+// identifiant sauvegardé et titre affiché
+const FIRST_BLOOD: (&str, &str) = ("first_blood", "First blood");
+const PERFECT_WAVE: (&str, &str) = ("perfect_wave", "Perfect wave");
+const UNTOUCHABLE: (&str, &str) = ("untouchable", "Untouchable");
+const CITY_GUARDIAN: (&str, &str) = ("city_guardian", "City guardian");
+const CENTURION: (&str, &str) = ("centurion", "Centurion");
+
+pub struct Achievements {
+    unlocked: Vec<String>,
+    // compteurs de la partie et de la vague en cours
+    kills: u32,
+    ship_hit: bool,
+    building_lost: bool,
+    // titres à annoncer et temps restant pour le premier
+    toasts: Vec<String>,
+    toast_time: f32,
+}
+
+impl Achievements {
+    pub fn new() -> Achievements {
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        let unlocked = storage
+            .get(STORAGE_KEY)
+            .map(|list| {
+                list.split(',')
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Achievements {
+            unlocked,
+            kills: 0,
+            ship_hit: false,
+            building_lost: false,
+            toasts: vec![],
+            toast_time: 0.0,
+        }
+    }
+
+    // nouvelle partie : les compteurs repartent de zéro
+    pub fn new_game(&mut self) {
+        self.kills = 0;
+        self.new_wave();
+        self.toasts.clear();
+    }
+
+    fn new_wave(&mut self) {
+        self.ship_hit = false;
+        self.building_lost = false;
+    }
+
+    // débloque un succès une seule fois, le sauvegarde et l'annonce
+    fn unlock(&mut self, (id, title): (&str, &str)) {
+        if self.unlocked.iter().any(|unlocked| unlocked == id) {
+            return;
+        }
+        info!("achievement unlocked: {}", title);
+        self.unlocked.push(id.to_string());
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        storage.set(STORAGE_KEY, &self.unlocked.join(","));
+        if self.toasts.is_empty() {
+            self.toast_time = TOAST_DURATION;
+        }
+        self.toasts.push(title.to_string());
+    }
+
+    // les annonces passent l'une après l'autre
+    pub fn update(&mut self, delta_time: f32) {
+        if self.toasts.is_empty() {
+            return;
+        }
+        self.toast_time -= delta_time;
+        if self.toast_time <= 0.0 {
+            self.toasts.remove(0);
+            self.toast_time = TOAST_DURATION;
+        }
+    }
+
+    pub fn display(&self, font: Option<&Font>) {
+        if let Some(title) = self.toasts.first() {
+            let text_params = TextParams {
+                font_size: 25,
+                font,
+                color: GOLD,
+                font_scale: 1.0,
+                ..Default::default()
+            };
+            draw_centered(
+                &format!("Achievement unlocked: {}", title),
+                screen_height() - 140.0,
+                &text_params,
+            );
+        }
+    }
+}
+
+impl Subscriber for Achievements {
+    fn notify(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyDestroyed { .. } => {
+                self.kills += 1;
+                self.unlock(FIRST_BLOOD);
+                if self.kills >= CENTURION_KILLS {
+                    self.unlock(CENTURION);
+                }
+            }
+            GameEvent::ShipHit { .. } => self.ship_hit = true,
+            GameEvent::BuildingDestroyed { .. } => self.building_lost = true,
+            GameEvent::WaveCleared(summary) => {
+                if summary.destroyed == summary.count {
+                    self.unlock(PERFECT_WAVE);
+                }
+                if !self.ship_hit {
+                    self.unlock(UNTOUCHABLE);
+                }
+                if !self.building_lost {
+                    self.unlock(CITY_GUARDIAN);
+                }
+                self.new_wave();
+            }
+            _ => {}
+        }
+    }
+}
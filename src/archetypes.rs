@@ -0,0 +1,132 @@
+/* Structure Archetypes (types d'ennemis lus dans un fichier) */
+
+use crate::collider::ColliderDef;
+use crate::data::*;
+use nanoserde::DeRon;
+
+pub const DEFAULT_ARCHETYPES_FILE: &str = "enemies.ron";
+// les types d'ennemis livrés avec le jeu
+const DEFAULT_ARCHETYPES: &str = include_str!("../assets/enemies.ron");
+
+// façon dont un ennemi se déplace
+#[derive(Clone, Copy, PartialEq)]
+pub enum Behaviour {
+    Straight,
+    Zigzag,
+    Dive,
+}
+
+impl Behaviour {
+    fn from_name(name: &str) -> Option<Behaviour> {
+        match name {
+            "straight" => Some(Behaviour::Straight),
+            "zigzag" => Some(Behaviour::Zigzag),
+            "dive" => Some(Behaviour::Dive),
+            _ => None,
+        }
+    }
+}
+
+#[derive(DeRon, Clone)]
+pub struct ArchetypeDef {
+    pub name: String,
+    pub texture: String,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub size_min: f32,
+    pub size_max: f32,
+    pub speed_min: f32,
+    pub speed_max: f32,
+    pub life: i32,
+    pub score: u32,
+    pub behaviour: String,
+    pub weight: f32,
+    // forme de collision dans l'image ; par défaut toute l'image
+    #[nserde(default)]
+    pub collider: ColliderDef,
+}
+
+#[derive(DeRon)]
+struct ArchetypesFile {
+    archetypes: Vec<ArchetypeDef>,
+}
+
+// un type d'ennemi vérifié
+#[derive(Clone)]
+pub struct Archetype {
+    pub def: ArchetypeDef,
+    pub behaviour: Behaviour,
+}
+
+#[derive(Clone)]
+pub struct Archetypes {
+    pub list: Vec<Archetype>,
+}
+
+impl Archetypes {
+    // lecture et vérification d'un fichier de types d'ennemis
+    pub fn parse(file: &str, text: &str) -> Result<Archetypes, DataError> {
+        let data = ArchetypesFile::deserialize_ron(text)
+            .map_err(|e| DataError::Parse(file.to_string(), e))?;
+        if data.archetypes.is_empty() {
+            return Err(DataError::Empty(file.to_string()));
+        }
+        let mut list = vec![];
+        for (i, def) in data.archetypes.into_iter().enumerate() {
+            let invalid = |message: &str| DataError::Invalid {
+                file: file.to_string(),
+                entry: i,
+                name: def.name.clone(),
+                message: message.to_string(),
+            };
+            if def.name.is_empty() {
+                return Err(invalid("name is empty"));
+            }
+            if !def.texture.ends_with(".png") {
+                return Err(invalid("texture must be a .png file"));
+            }
+            if def.width == 0 || def.height == 0 || def.frames == 0 {
+                return Err(invalid("width, height and frames must be greater than 0"));
+            }
+            if !(def.size_min > 0.0 && def.size_min <= def.size_max) {
+                return Err(invalid("size_min must be between 0 and size_max"));
+            }
+            if !(def.speed_min >= 0.0 && def.speed_min <= def.speed_max) {
+                return Err(invalid("speed_min must be between 0 and speed_max"));
+            }
+            if def.life <= 0 {
+                return Err(invalid("life must be greater than 0"));
+            }
+            if !def.weight.is_finite() || def.weight <= 0.0 {
+                return Err(invalid("weight must be greater than 0"));
+            }
+            let Some(behaviour) = Behaviour::from_name(&def.behaviour) else {
+                return Err(invalid("behaviour must be straight, zigzag or dive"));
+            };
+            def.collider.check().map_err(invalid)?;
+            list.push(Archetype { def, behaviour });
+        }
+        Ok(Archetypes { list })
+    }
+
+    // les types livrés avec le jeu
+    pub fn default_archetypes() -> Archetypes {
+        Archetypes::parse(DEFAULT_ARCHETYPES_FILE, DEFAULT_ARCHETYPES)
+            .expect("invalid built-in enemies")
+    }
+
+    // chargement depuis le dossier assets ; en cas d'erreur on garde les types par défaut
+    pub async fn load(file: &str) -> (Archetypes, Option<DataError>) {
+        load_or_default(
+            file,
+            |text| Archetypes::parse(file, text),
+            Archetypes::default_archetypes,
+        )
+        .await
+    }
+
+    pub fn get(&self, kind: usize) -> &Archetype {
+        &self.list[kind]
+    }
+}
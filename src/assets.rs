@@ -0,0 +1,358 @@
+/* Structure Assets (manifeste des ressources du jeu et chargement avec écran de progression) */
+
+use crate::archetypes::Archetypes;
+use crate::atlas::*;
+use crate::level::Level;
+use crate::music::MusicList;
+use crate::sound_variants::encode_wav;
+use crate::text_display::display_loading;
+use macroquad::audio::{Sound, load_sound_from_bytes};
+use macroquad::experimental::coroutines::{Coroutine, start_coroutine};
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+// ce que devient un fichier une fois chargé
+#[derive(Clone, Copy, PartialEq)]
+pub enum AssetKind {
+    // planche de sprites, rangée dans l'atlas ; l'image reste en mémoire (masques de collision)
+    Texture,
+    // texture isolée, hors de l'atlas (particules)
+    Particle,
+    // image seule (habillage du menu)
+    Image,
+    // bruitage, dont le fichier reste en mémoire (SoundVariants)
+    Sound,
+    // morceau de musique, joué tel quel
+    Music,
+    Font,
+}
+
+// ressources fixes du jeu ; les planches des bâtiments et des ennemis et les musiques
+// viennent des fichiers de données
+const MANIFEST: [(&str, AssetKind); 14] = [
+    ("ship.png", AssetKind::Texture),
+    ("heart.png", AssetKind::Texture),
+    ("heart2.png", AssetKind::Texture),
+    ("ammo.png", AssetKind::Texture),
+    ("ammo2.png", AssetKind::Texture),
+    ("laser-bolts.png", AssetKind::Texture),
+    ("explosion.png", AssetKind::Particle),
+    ("window_background.png", AssetKind::Image),
+    ("button_background.png", AssetKind::Image),
+    ("button_clicked_background.png", AssetKind::Image),
+    ("laser.wav", AssetKind::Sound),
+    ("explosion.wav", AssetKind::Sound),
+    ("test.ttf", AssetKind::Font),
+    ("atari_games.ttf", AssetKind::Font),
+];
+
+// temps de décodage accordé à chaque frame de l'écran de chargement, en secondes
+const FRAME_BUDGET: f64 = 1.0 / 30.0;
+
+// taille des cases du damier qui remplace une image manquante
+const CHECKER_SIZE: u32 = 8;
+// nombre d'échantillons du son muet qui remplace un son manquant
+const SILENT_SAMPLES: usize = 64;
+
+#[derive(Debug)]
+pub enum AssetError {
+    // fichier absent ou téléchargement échoué
+    Load(String, String),
+    // fichier lu mais illisible (image, son ou police corrompus)
+    Decode(String, String),
+}
+
+impl AssetError {
+    pub fn file(&self) -> &str {
+        match self {
+            AssetError::Load(file, _) | AssetError::Decode(file, _) => file,
+        }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Load(file, message) => write!(f, "{}: {}", file, message),
+            AssetError::Decode(file, message) => {
+                write!(f, "{}: corrupt file ({})", file, message)
+            }
+        }
+    }
+}
+
+// fichier en cours de téléchargement
+type Loading<'a> = (
+    &'a str,
+    AssetKind,
+    Coroutine<Result<Vec<u8>, macroquad::Error>>,
+);
+
+// liste de tous les fichiers à charger avant la première frame du jeu
+pub struct AssetManifest {
+    pub entries: Vec<(String, AssetKind)>,
+}
+
+impl AssetManifest {
+    pub fn new(level: &Level, archetypes: &Archetypes, music: &MusicList) -> AssetManifest {
+        let mut manifest = AssetManifest { entries: vec![] };
+        for (file, kind) in MANIFEST {
+            manifest.add(file, kind);
+        }
+        for def in &level.buildings {
+            manifest.add(&def.texture, AssetKind::Texture);
+        }
+        for archetype in &archetypes.list {
+            manifest.add(&archetype.def.texture, AssetKind::Texture);
+        }
+        for file in music.files() {
+            manifest.add(file, AssetKind::Music);
+        }
+        manifest
+    }
+
+    // un fichier utilisé plusieurs fois n'est chargé qu'une fois
+    fn add(&mut self, file: &str, kind: AssetKind) {
+        if !self.entries.iter().any(|(entry, _)| entry == file) {
+            self.entries.push((file.to_string(), kind));
+        }
+    }
+}
+
+// ressources chargées, par nom de fichier ; celles qui manquent sont remplacées
+// par un damier, un son muet ou la police par défaut
+pub struct Assets {
+    // textures isolées, hors de l'atlas
+    textures: HashMap<String, Texture2D>,
+    images: HashMap<String, Image>,
+    sounds: HashMap<String, Sound>,
+    // fichiers WAV des bruitages, gardés pour en calculer des variantes (SoundVariants)
+    sound_bytes: HashMap<String, Vec<u8>>,
+    fonts: HashMap<String, Vec<u8>>,
+    // type de chaque fichier du manifeste
+    kinds: HashMap<String, AssetKind>,
+    checker_image: Image,
+    checker_texture: Texture2D,
+    silence: Sound,
+    // toutes les textures du manifeste et le damier, regroupées en pages
+    pub atlas: Atlas,
+    // fichiers qui n'ont pas pu être chargés
+    pub errors: Vec<AssetError>,
+}
+
+impl Assets {
+    // tous les fichiers sont demandés en même temps (requêtes parallèles sur le web) ;
+    // on les décode au fur et à mesure de leur arrivée, derrière une barre de progression
+    pub async fn load(manifest: &AssetManifest) -> Assets {
+        let checker_image = checkerboard();
+        let mut assets = Assets {
+            textures: HashMap::new(),
+            images: HashMap::new(),
+            sounds: HashMap::new(),
+            sound_bytes: HashMap::new(),
+            fonts: HashMap::new(),
+            kinds: manifest.entries.iter().cloned().collect(),
+            checker_texture: Texture2D::from_image(&checker_image),
+            checker_image,
+            silence: load_sound_from_bytes(&silent_wav())
+                .await
+                .expect("invalid built-in silent sound"),
+            atlas: Atlas::build(&[]),
+            errors: vec![],
+        };
+        let mut pending: Vec<Loading> = manifest
+            .entries
+            .iter()
+            .map(|(file, kind)| {
+                let path = file.clone();
+                let coroutine = start_coroutine(async move { load_file(&path).await });
+                (file.as_str(), *kind, coroutine)
+            })
+            .collect();
+        let total = pending.len();
+        while !pending.is_empty() {
+            let frame_start = get_time();
+            while get_time() - frame_start < FRAME_BUDGET {
+                let Some(index) = pending.iter().position(|(_, _, loading)| loading.is_done())
+                else {
+                    break;
+                };
+                let (file, kind, loading) = pending.remove(index);
+                let result = match loading.retrieve() {
+                    Some(Ok(bytes)) => assets.decode(file, kind, &bytes).await,
+                    Some(Err(e)) => Err(AssetError::Load(file.to_string(), describe(&e))),
+                    None => Err(AssetError::Load(
+                        file.to_string(),
+                        "interrupted".to_string(),
+                    )),
+                };
+                if let Err(e) = result {
+                    error!("{}", e);
+                    assets.errors.push(e);
+                }
+            }
+            if let Some((file, _, _)) = pending.first() {
+                clear_background(BLACK);
+                display_loading(total - pending.len(), total, file);
+                next_frame().await;
+            }
+        }
+        assets.pack();
+        assets
+    }
+
+    // relit un fichier du manifeste (rechargement à chaud) ; en cas d'erreur l'ancienne version reste
+    pub async fn reload(&mut self, file: &str) {
+        let Some(kind) = self.kinds.get(file).copied() else {
+            return;
+        };
+        self.errors.retain(|e| e.file() != file);
+        let result = match load_file(file).await {
+            Ok(bytes) => self.decode(file, kind, &bytes).await,
+            Err(e) => Err(AssetError::Load(file.to_string(), describe(&e))),
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            self.errors.push(e);
+        }
+        if kind == AssetKind::Texture {
+            self.pack();
+        }
+    }
+
+    // rangement des textures dans l'atlas ; le damier y est rangé sous un nom vide
+    fn pack(&mut self) {
+        let mut images: Vec<(&str, &Image)> = self
+            .kinds
+            .iter()
+            .filter(|(_, kind)| **kind == AssetKind::Texture)
+            .filter_map(|(file, _)| Some((file.as_str(), self.images.get(file)?)))
+            .collect();
+        images.sort_by_key(|(file, _)| *file);
+        images.push(("", &self.checker_image));
+        self.atlas = Atlas::build(&images);
+    }
+
+    // fichiers ajoutés au manifeste depuis le chargement (planches d'un niveau modifié)
+    pub async fn load_missing(&mut self, manifest: &AssetManifest) {
+        for (file, kind) in &manifest.entries {
+            if !self.kinds.contains_key(file) {
+                self.kinds.insert(file.clone(), *kind);
+                self.reload(file).await;
+            }
+        }
+    }
+
+    async fn decode(
+        &mut self,
+        file: &str,
+        kind: AssetKind,
+        bytes: &[u8],
+    ) -> Result<(), AssetError> {
+        let corrupt = |e: macroquad::Error| AssetError::Decode(file.to_string(), describe(&e));
+        let file = file.to_string();
+        match kind {
+            AssetKind::Particle => {
+                let image = Image::from_file_with_format(bytes, None).map_err(corrupt)?;
+                self.textures.insert(file, Texture2D::from_image(&image));
+            }
+            AssetKind::Texture | AssetKind::Image => {
+                let image = Image::from_file_with_format(bytes, None).map_err(corrupt)?;
+                self.images.insert(file, image);
+            }
+            AssetKind::Sound => {
+                let sound = load_sound_from_bytes(bytes).await.map_err(corrupt)?;
+                self.sound_bytes.insert(file.clone(), bytes.to_vec());
+                self.sounds.insert(file, sound);
+            }
+            AssetKind::Music => {
+                let sound = load_sound_from_bytes(bytes).await.map_err(corrupt)?;
+                self.sounds.insert(file, sound);
+            }
+            AssetKind::Font => {
+                // la police n'est gardée que si elle se lit
+                load_ttf_font_from_bytes(bytes).map_err(corrupt)?;
+                self.fonts.insert(file, bytes.to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    // type d'un fichier du manifeste ; None pour les autres fichiers
+    pub fn kind(&self, file: &str) -> Option<AssetKind> {
+        self.kinds.get(file).copied()
+    }
+
+    // planche rangée dans l'atlas, pour les sprites
+    pub fn sprite(&self, file: &str) -> AtlasSprite {
+        self.atlas
+            .get(file)
+            .or_else(|| self.atlas.get(""))
+            .expect("checkerboard missing from the atlas")
+            .clone()
+    }
+
+    // texture isolée, pour ce qui ne passe pas par l'atlas (particules)
+    pub fn texture(&self, file: &str) -> Texture2D {
+        self.textures
+            .get(file)
+            .unwrap_or(&self.checker_texture)
+            .clone()
+    }
+
+    pub fn image(&self, file: &str) -> &Image {
+        self.images.get(file).unwrap_or(&self.checker_image)
+    }
+
+    pub fn sound(&self, file: &str) -> Sound {
+        self.sounds.get(file).unwrap_or(&self.silence).clone()
+    }
+
+    // None si le son n'est pas un WAV ou n'a pas pu être chargé
+    pub fn sound_bytes(&self, file: &str) -> Option<&[u8]> {
+        self.sound_bytes.get(file).map(|bytes| bytes.as_slice())
+    }
+
+    // None : la police par défaut de macroquad
+    pub fn font(&self, file: &str) -> Option<Font> {
+        self.font_bytes(file)
+            .and_then(|bytes| load_ttf_font_from_bytes(bytes).ok())
+    }
+
+    // police brute, pour l'habillage du menu
+    pub fn font_bytes(&self, file: &str) -> Option<&[u8]> {
+        self.fonts.get(file).map(|bytes| bytes.as_slice())
+    }
+}
+
+// message lisible d'une erreur de macroquad
+fn describe(e: &macroquad::Error) -> String {
+    match e {
+        macroquad::Error::FileError { kind, .. } => kind.to_string(),
+        macroquad::Error::ImageError(e) => e.to_string(),
+        macroquad::Error::FontError(message) | macroquad::Error::UnknownError(message) => {
+            message.to_string()
+        }
+        e => format!("{:?}", e),
+    }
+}
+
+// damier magenta et noir, bien visible à la place d'une image manquante
+fn checkerboard() -> Image {
+    let size = CHECKER_SIZE * 8;
+    let mut image = Image::gen_image_color(size as u16, size as u16, BLACK);
+    for y in 0..size {
+        for x in 0..size {
+            if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+                image.set_pixel(x, y, MAGENTA);
+            }
+        }
+    }
+    image
+}
+
+// quelques échantillons de silence au format WAV
+fn silent_wav() -> Vec<u8> {
+    encode_wav(1, &[0; SILENT_SAMPLES])
+}
@@ -0,0 +1,195 @@
+/* Structure Atlas (planches de sprites regroupées dans quelques grandes pages de texture) */
+
+use macroquad::prelude::*;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+// taille d'une page, acceptée par tous les navigateurs (WebGL 1)
+const PAGE_SIZE: u32 = 2048;
+// marge transparente autour de chaque planche, contre les bavures entre voisines
+const PADDING: u32 = 2;
+
+thread_local! {
+    // page du dernier sprite dessiné et nombre de changements de page depuis le début de la frame
+    static LAST_PAGE: Cell<Option<usize>> = const { Cell::new(None) };
+    static SWITCHES: Cell<u32> = const { Cell::new(0) };
+}
+
+// une planche rangée dans une page de l'atlas
+#[derive(Clone)]
+pub struct AtlasSprite {
+    texture: Texture2D,
+    page: usize,
+    // position de la planche dans la page
+    rect: Rect,
+}
+
+impl AtlasSprite {
+    // rectangle source d'une image de la planche (AnimatedSprite), ramené dans la page
+    pub fn source(&self, frame: Option<Rect>) -> Rect {
+        match frame {
+            Some(frame) => Rect::new(
+                self.rect.x + frame.x,
+                self.rect.y + frame.y,
+                frame.w,
+                frame.h,
+            ),
+            None => self.rect,
+        }
+    }
+
+    // comme draw_texture_ex, la source étant relative à la planche
+    pub fn draw(&self, x: f32, y: f32, color: Color, params: DrawTextureParams) {
+        if LAST_PAGE.get() != Some(self.page) {
+            LAST_PAGE.set(Some(self.page));
+            SWITCHES.set(SWITCHES.get() + 1);
+        }
+        let source = self.source(params.source);
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            color,
+            DrawTextureParams {
+                source: Some(source),
+                dest_size: params.dest_size.or(Some(source.size())),
+                ..params
+            },
+        );
+    }
+}
+
+// changements de page pendant la frame (chacun coupe le lot de dessins en cours)
+pub fn texture_switches() -> u32 {
+    SWITCHES.get()
+}
+
+pub fn reset_texture_switches() {
+    LAST_PAGE.set(None);
+    SWITCHES.set(0);
+}
+
+pub struct Atlas {
+    pages: Vec<Texture2D>,
+    sprites: HashMap<String, AtlasSprite>,
+}
+
+impl Atlas {
+    // rangement par étagères : les planches, de la plus haute à la plus basse, sont posées
+    // de gauche à droite ; une étagère pleine en ouvre une nouvelle, une page pleine aussi
+    pub fn build(images: &[(&str, &Image)]) -> Atlas {
+        let mut order: Vec<usize> = (0..images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(images[i].1.height));
+        let mut pages: Vec<Image> = vec![];
+        let mut places = vec![(0, Rect::new(0.0, 0.0, 0.0, 0.0)); images.len()];
+        let (mut x, mut y, mut shelf_height) = (PAGE_SIZE, PAGE_SIZE, 0);
+        for i in order {
+            let image = images[i].1;
+            let (width, height) = (image.width as u32, image.height as u32);
+            if x + width + PADDING > PAGE_SIZE {
+                // étagère suivante
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            if y + height + PADDING > PAGE_SIZE {
+                pages.push(Image::gen_image_color(
+                    PAGE_SIZE as u16,
+                    PAGE_SIZE as u16,
+                    BLANK,
+                ));
+                (x, y, shelf_height) = (0, 0, 0);
+            }
+            let page = pages.last_mut().unwrap();
+            let (left, top) = (x + PADDING, y + PADDING);
+            // copie ligne par ligne ; une planche plus grande qu'une page est coupée
+            let row = (width.min(PAGE_SIZE - left) * 4) as usize;
+            for sy in 0..height.min(PAGE_SIZE - top) {
+                let from = (sy * width * 4) as usize;
+                let to = (((top + sy) * PAGE_SIZE + left) * 4) as usize;
+                page.bytes[to..to + row].copy_from_slice(&image.bytes[from..from + row]);
+            }
+            places[i] = (
+                pages.len() - 1,
+                Rect::new(left as f32, top as f32, width as f32, height as f32),
+            );
+            x += width + PADDING;
+            shelf_height = shelf_height.max(height + PADDING);
+        }
+        let pages: Vec<Texture2D> = pages
+            .iter()
+            .map(|page| {
+                let texture = Texture2D::from_image(page);
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+            .collect();
+        let sprites = images
+            .iter()
+            .zip(places)
+            .map(|((file, _), (page, rect))| {
+                let sprite = AtlasSprite {
+                    texture: pages[page].clone(),
+                    page,
+                    rect,
+                };
+                (file.to_string(), sprite)
+            })
+            .collect();
+        Atlas { pages, sprites }
+    }
+
+    pub fn get(&self, file: &str) -> Option<&AtlasSprite> {
+        self.sprites.get(file)
+    }
+
+    // vue de mise au point : les pages côte à côte, réduites à la hauteur de l'écran,
+    // avec le contour de chaque planche
+    pub fn display_pages(&self) {
+        let size = (screen_height() - 20.0).min(screen_width() / self.pages.len().max(1) as f32);
+        let side = size - 10.0;
+        let scale = side / PAGE_SIZE as f32;
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+        for (i, page) in self.pages.iter().enumerate() {
+            let x = 10.0 + i as f32 * size;
+            draw_texture_ex(
+                page,
+                x,
+                10.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(side, side)),
+                    ..Default::default()
+                },
+            );
+            draw_rectangle_lines(x, 10.0, side, side, 1.0, GRAY);
+            for sprite in self.sprites.values().filter(|sprite| sprite.page == i) {
+                draw_rectangle_lines(
+                    x + sprite.rect.x * scale,
+                    10.0 + sprite.rect.y * scale,
+                    sprite.rect.w * scale,
+                    sprite.rect.h * scale,
+                    1.0,
+                    GREEN,
+                );
+            }
+        }
+        draw_text(
+            &format!(
+                "{} page(s), {} sheets",
+                self.pages.len(),
+                self.sprites.len()
+            ),
+            10.0,
+            screen_height() - 10.0,
+            20.0,
+            GREEN,
+        );
+    }
+}
@@ -1,212 +1,162 @@
 /* Structure BuildingsSet (gestion des bâtiments) */
 
 use crate::Shape;
+use crate::assets::Assets;
+use crate::atlas::AtlasSprite;
+use crate::level::*;
+use crate::masks::MaskLibrary;
+use crate::timestep::ANIMATION_FPS;
 use macroquad::experimental::animation::AnimatedSprite;
 use macroquad::experimental::animation::Animation;
 use macroquad::prelude::*;
 use std::cmp;
-pub struct BuildingData {
-    pub texture: Texture2D,
-    pub width: u32,
-    pub height: u32,
-    pub x: f32,
-    pub name: String,
-}
+use std::collections::HashMap;
+
+// images de l'animation du feu (ligne 1 des planches)
+const FIRE_FRAMES: u32 = 10;
 
 pub struct Building {
-    texture: Texture2D,
-    sprite: AnimatedSprite,
     pub shape: Shape,
+    pub width: u32,
+    pub height: u32,
+    pub name: String,
+    pub texture: String,
 }
 
-impl BuildingData {
-    pub fn get_building(&mut self) -> Building {
+impl BuildingDef {
+    pub fn get_building(&self, screen_height: f32, masks: &MaskLibrary) -> Building {
+        let y = screen_height - (self.height / 2) as f32;
+        let collider = self.collider.to_collider(
+            self.width as f32,
+            self.height as f32,
+            1.0,
+            masks.get(&self.texture),
+        );
         Building {
             shape: Shape {
                 x: self.x,
-                y: screen_height() - (self.height / 2) as f32,
+                y,
                 speed: 0.0,
                 size: cmp::max(self.height, self.width) as f32,
                 collided: false,
-                life:3
+                life: self.life,
+                prev_x: self.x,
+                prev_y: y,
+                velocity: Vec2::ZERO,
+                kind: 0,
+                collider,
             },
+            width: self.width,
+            height: self.height,
+            name: self.name.clone(),
             texture: self.texture.clone(),
-            sprite: AnimatedSprite::new(
-                self.width,
-                self.height,
-                &[Animation {
-                    name: self.name.clone(),
-                    row: 0,
-                    frames: 1,
-                    fps: 12,
-                },
-                Animation {
-                    name: "fire".to_string(),
-                    row: 1,
-                    frames: 10,
-                    fps: 12,
-                },],
-                true,
-            ),
         }
     }
 }
+
 pub struct BuildingsSet {
     pub buildings: Vec<Building>,
-    pub temple_texture: Texture2D,
-    pub space_port_texture: Texture2D,
-    pub factory_texture: Texture2D,
-    pub labo_texture: Texture2D,
-    pub radio_texture: Texture2D,
-    pub greenhouse_texture: Texture2D,
-    pub greenhouse2_texture: Texture2D,
-    pub greenhouse3_texture: Texture2D,
-    pub rocket_texture: Texture2D,
+    // horloge des animations
+    time: f32,
 }
 
-impl BuildingsSet {
-    pub async fn new() -> BuildingsSet {
-        let temple_texture: Texture2D = load_texture("temple_temple_fire.png").await.expect("Couldn't load file");
-        temple_texture.set_filter(FilterMode::Nearest);
-
-        let space_port_texture: Texture2D = load_texture("space_port_space_port_fire.png").await.expect("Couldn't load file");
-        space_port_texture.set_filter(FilterMode::Nearest);
-
-        let factory_texture: Texture2D = load_texture("factory_factory_fire.png").await.expect("Couldn't load file");
-        factory_texture.set_filter(FilterMode::Nearest);
-
-        let labo_texture: Texture2D = load_texture("labo_labo_fire.png").await.expect("Couldn't load file");
-        labo_texture.set_filter(FilterMode::Nearest);
-
-        let radio_texture: Texture2D = load_texture("radio_radio_fire.png").await.expect("Couldn't load file");
-        radio_texture.set_filter(FilterMode::Nearest);
+// ressources d'affichage des bâtiments (textures et animations)
+pub struct BuildingsView {
+    textures: HashMap<String, AtlasSprite>,
+    sprites: Vec<AnimatedSprite>,
+}
 
-        let greenhouse_texture: Texture2D = load_texture("greenhouse_greenhouse_fire.png").await.expect("Couldn't load file");
-        greenhouse_texture.set_filter(FilterMode::Nearest);
+impl BuildingsSet {
+    pub fn new() -> BuildingsSet {
+        BuildingsSet {
+            buildings: vec![],
+            time: 0.0,
+        }
+    }
 
-        let greenhouse2_texture: Texture2D = load_texture("greenhouse2_greenhouse2_fire.png").await.expect("Couldn't load file");
-        greenhouse2_texture.set_filter(FilterMode::Nearest);
+    // construction de la ville décrite par le niveau
+    pub fn reset(&mut self, screen_height: f32, level: &Level, masks: &MaskLibrary) {
+        self.buildings = level
+            .buildings
+            .iter()
+            .map(|def| def.get_building(screen_height, masks))
+            .collect();
+        self.time = 0.0;
+    }
 
-        let greenhouse3_texture: Texture2D = load_texture("greenhouse3_greenhouse3_fire.png").await.expect("Couldn't load file");
-        greenhouse3_texture.set_filter(FilterMode::Nearest);
+    // niveau modifié en cours de partie : chaque bâtiment garde les dégâts de celui
+    // du même nom, même si la ville a été réordonnée
+    pub fn reload(&mut self, screen_height: f32, level: &Level, masks: &MaskLibrary) {
+        let mut old = std::mem::take(&mut self.buildings);
+        let time = self.time;
+        self.reset(screen_height, level, masks);
+        self.time = time;
+        for building in self.buildings.iter_mut() {
+            let Some(i) = old.iter().position(|old| old.name == building.name) else {
+                continue;
+            };
+            let old = old.remove(i);
+            building.shape.life = building.shape.life.min(old.shape.life);
+            building.shape.collided = old.shape.collided;
+        }
+    }
 
-        let rocket_texture: Texture2D = load_texture("rocket_rocket_fire.png").await.expect("Couldn't load file");
-        rocket_texture.set_filter(FilterMode::Nearest);
+    // image de l'animation du feu
+    pub fn fire_frame(&self) -> u32 {
+        (self.time * ANIMATION_FPS) as u32 % FIRE_FRAMES
+    }
 
-        let buildings: Vec<Building> = vec![];
-        BuildingsSet {
-            buildings,
-            temple_texture,
-            space_port_texture,
-            factory_texture,
-            labo_texture,
-            radio_texture,
-            greenhouse_texture,
-            greenhouse2_texture,
-            greenhouse3_texture,
-            rocket_texture,
+    // avance les animations ; un bâtiment touché passe à la ligne du feu
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        let fire_frame = self.fire_frame();
+        for building in &mut self.buildings {
+            if building.shape.collided {
+                building.shape.collider.set_frame(1, fire_frame);
+            } else {
+                building.shape.collider.set_frame(0, 0);
+            }
         }
     }
 
-    pub fn reset(&mut self) {
-        let mut data: Vec<BuildingData> = vec![];
-        let mut x = 29.;
-         data.push(BuildingData {
-            texture: self.temple_texture.clone(),
-            width: 86,
-            height: 80,
-            x: x,
-            name: "temple".to_string(),
-        });
-
-        x += 90.0;
-        data.push(BuildingData {
-            texture: self.space_port_texture.clone(),
-            width: 90,
-            height: 58,
-            x: x,
-            name: "space_port".to_string(),
-        });
-        
-        x += 95.0;
-        data.push(BuildingData {
-            texture: self.factory_texture.clone(),
-            width: 90,
-            height: 58,
-            x: x,
-            name: "factory".to_string(),
-        });
-        
-        x += 80.0;
-        data.push(BuildingData {
-            texture: self.labo_texture.clone(),
-            width: 92,
-            height: 67,
-            x: x,
-            name: "labo".to_string(),
-        });
-
-        x += 80.0;
-        data.push(BuildingData {
-            texture: self.radio_texture.clone(),
-            width: 90,
-            height: 53,
-            x: x,
-            name: "radio".to_string(),
-        });
-
-        x += 100.0;
-        data.push(BuildingData {
-            texture: self.greenhouse_texture.clone(),
-            width: 89,
-            height: 58,
-            x: x,
-            name: "greenhouse".to_string(),
-        });
-
-        x += 100.0;
-        data.push(BuildingData {
-            texture: self.greenhouse2_texture.clone(),
-            width: 88,
-            height: 58,
-            x: x,
-            name: "greenhouse2".to_string(),
-        });
-
-        x += 100.0;
-        data.push(BuildingData {
-            texture: self.greenhouse3_texture.clone(),
-            width: 89,
-            height: 58,
-            x: x,
-            name: "greenhouse3".to_string(),
-        });
-
-        x += 100.0;
-        data.push(BuildingData {
-            texture: self.rocket_texture.clone(),
-            width: 89,
-            height: 104,
-            x: x,
-            name: "rocket".to_string(),
-        });
-        
-        for mut d in data {
-            self.buildings.push(d.get_building());
+    pub fn all_destroyed(&self) -> bool {
+        self.buildings
+            .iter()
+            .all(|building| building.shape.collided)
+    }
+}
+
+impl BuildingsView {
+    // planches de sprites utilisées par le niveau
+    pub fn new(level: &Level, assets: &Assets) -> BuildingsView {
+        let mut textures = HashMap::new();
+        for def in &level.buildings {
+            if textures.contains_key(&def.texture) {
+                continue;
+            }
+            let texture = assets.sprite(&def.texture);
+            textures.insert(def.texture.clone(), texture);
+        }
+        BuildingsView {
+            textures,
+            sprites: vec![],
         }
     }
 
     // affichage des bâtiments
-    pub fn display(&mut self) {
-        for building in &self.buildings {
-            let building_frame = building.sprite.frame();
-            draw_texture_ex(
-                &building.texture,
-                building.shape.x - building.shape.size / 2.0,
-                building.shape.y - building.shape.size / 2.0,
+    pub fn display(&self, buildings: &BuildingsSet) {
+        for (building, sprite) in buildings.buildings.iter().zip(&self.sprites) {
+            let Some(texture) = self.textures.get(&building.texture) else {
+                continue;
+            };
+            let building_frame = sprite.frame();
+            // à la taille de l'image, comme sa forme de collision
+            texture.draw(
+                building.shape.x - building.width as f32 / 2.0,
+                building.shape.y - building.height as f32 / 2.0,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(vec2(building.shape.size, building.shape.size)),
+                    dest_size: Some(vec2(building.width as f32, building.height as f32)),
                     source: Some(building_frame.source_rect),
                     ..Default::default()
                 },
@@ -214,36 +164,77 @@ impl BuildingsSet {
         }
     }
 
-    pub fn get_list(&mut self) -> Vec<&mut Shape> {
-        self.buildings
-            .iter_mut()
-            .map(|building| &mut building.shape)
-            .collect()
-    }
-
-    // mise à jour des batiments
-    pub fn update(&mut self) {
-        for building in &mut self.buildings {
+    // mise à jour des animations (feu si le bâtiment est détruit)
+    pub fn update(&mut self, buildings: &BuildingsSet) {
+        let outdated = self.sprites.len() != buildings.buildings.len()
+            || buildings
+                .buildings
+                .iter()
+                .zip(&self.sprites)
+                .any(|(building, sprite)| {
+                    sprite.frame().dest_size != vec2(building.width as f32, building.height as f32)
+                });
+        if outdated {
+            self.sprites = buildings
+                .buildings
+                .iter()
+                .map(BuildingsView::get_sprite)
+                .collect();
+        }
+        for (building, sprite) in buildings.buildings.iter().zip(&mut self.sprites) {
             if building.shape.collided {
-                building.sprite.set_animation(1);
+                sprite.set_animation(1);
+                sprite.set_frame(buildings.fire_frame());
             } else {
-                building.sprite.set_animation(0);
+                sprite.set_animation(0);
+                sprite.set_frame(0);
             }
-            building.sprite.update();
         }
     }
 
-    pub fn all_destroyed(&mut self) -> bool {
-        let mut destroyed= true;
-        for building in &mut self.buildings {
-            if building.shape.collided {
-                building.sprite.set_animation(1);
-            } else {
-                building.sprite.set_animation(0);
-                destroyed = false;
-            }
-            building.sprite.update();
+    fn get_sprite(building: &Building) -> AnimatedSprite {
+        AnimatedSprite::new(
+            building.width,
+            building.height,
+            &[
+                Animation {
+                    name: building.name.clone(),
+                    row: 0,
+                    frames: 1,
+                    fps: ANIMATION_FPS as u32,
+                },
+                Animation {
+                    name: "fire".to_string(),
+                    row: 1,
+                    frames: FIRE_FRAMES,
+                    fps: ANIMATION_FPS as u32,
+                },
+            ],
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload_keeps_damage_by_building_name() {
+        let masks = MaskLibrary::new();
+        let mut level = Level::default_city();
+        let mut set = BuildingsSet::new();
+        set.reset(800.0, &level, &masks);
+        let damaged = set.buildings[0].name.clone();
+        set.buildings[0].shape.life = 1;
+        set.buildings[0].shape.collided = true;
+        // le bâtiment touché passe en dernier dans le fichier
+        level.buildings.rotate_left(1);
+        set.reload(800.0, &level, &masks);
+        for building in &set.buildings {
+            assert_eq!(building.name == damaged, building.shape.collided);
         }
-        destroyed
+        let last = set.buildings.last().unwrap();
+        assert_eq!((last.name.as_str(), last.shape.life), (damaged.as_str(), 1));
     }
 }
@@ -1,22 +1,58 @@
 /* Structure BulletSet (gestion des balles) */
 
-use crate::{Collision, Shape};
+use crate::Shape;
+use crate::assets::Assets;
+use crate::atlas::AtlasSprite;
 use macroquad::experimental::animation::AnimatedSprite;
 use macroquad::experimental::animation::Animation;
 use macroquad::prelude::*;
 
 pub struct BulletsSet {
     pub bullets: Vec<Shape>,
-    pub bullet_texture: Texture2D,
+}
+
+// ressources d'affichage des balles (texture et animation)
+pub struct BulletsView {
+    pub bullet_texture: AtlasSprite,
     pub bullet_sprite: AnimatedSprite,
 }
 
 impl BulletsSet {
-    pub async fn new() -> BulletsSet {
-        let bullet_texture: Texture2D = load_texture("laser-bolts.png")
-            .await
-            .expect("Couldn't load file");
-        bullet_texture.set_filter(FilterMode::Nearest);
+    pub fn new() -> BulletsSet {
+        BulletsSet { bullets: vec![] }
+    }
+
+    // suppression des balles et des explosions
+    pub fn clear(&mut self) {
+        self.bullets.clear();
+    }
+
+    // Ajout d'une balle
+    pub fn push(&mut self, shape: Shape) {
+        self.bullets.push(shape);
+    }
+
+    // mise à jour de la positions des balles
+    pub fn update(&mut self, delta_time: f32, width: f32, height: f32) {
+        for bullet in &mut self.bullets {
+            bullet.x += bullet.velocity.x * delta_time;
+            bullet.y += bullet.velocity.y * delta_time;
+        }
+        self.bullets.retain(|bullet| !bullet.collided); // on vire les balles touchées
+        // on vire les balles sorties par n'importe quel bord de l'écran
+        self.bullets.retain(|bullet| {
+            let margin = bullet.size / 2.0;
+            bullet.x > -margin
+                && bullet.x < width + margin
+                && bullet.y > -margin
+                && bullet.y < height + margin
+        });
+    }
+}
+
+impl BulletsView {
+    pub fn new(assets: &Assets) -> BulletsView {
+        let bullet_texture = assets.sprite("laser-bolts.png");
         let mut bullet_sprite = AnimatedSprite::new(
             16,
             16,
@@ -37,62 +73,35 @@ impl BulletsSet {
             true,
         );
         bullet_sprite.set_animation(1);
-        BulletsSet {
-            bullets: vec![],
+        BulletsView {
             bullet_texture,
             bullet_sprite,
         }
     }
 
-    // suppression des balles et des explosions
-    pub fn clear(&mut self) {
-        self.bullets.clear();
-    }
-
-    // Ajout d'une balle
-    pub fn push(&mut self, shape: Shape) {
-        self.bullets.push(shape);
-    }
-
-    // affichage des balles et des explosions
-    pub fn display(&mut self) {
+    // affichage des balles
+    pub fn display(&self, bullets: &BulletsSet, alpha: f32) {
         let bullet_frame = self.bullet_sprite.frame();
-        for bullet in &self.bullets {
-            draw_texture_ex(
-                &self.bullet_texture,
-                bullet.x - bullet.size / 2.0,
-                bullet.y - bullet.size / 2.0,
+        for bullet in &bullets.bullets {
+            let position = bullet.lerp_position(alpha);
+            // le sprite pointe vers le haut : on le tourne dans le sens du déplacement
+            let rotation = bullet.velocity.x.atan2(-bullet.velocity.y);
+            self.bullet_texture.draw(
+                position.x - bullet.size / 2.0,
+                position.y - bullet.size / 2.0,
                 WHITE,
                 DrawTextureParams {
                     dest_size: Some(vec2(bullet.size, bullet.size)),
                     source: Some(bullet_frame.source_rect),
+                    rotation,
                     ..Default::default()
                 },
             );
         }
     }
 
-    // mise à jour de la positions des balles
-    pub fn update(&mut self, delta_time: f32) {
-        for bullet in &mut self.bullets {
-            bullet.y -= bullet.speed * delta_time;
-        }
-        self.bullets.retain(|bullet| !bullet.collided); // on vire les balles touchées
-        self.bullets
-            .retain(|bullet| bullet.y > 0.0 - bullet.size / 2.0); // on vire les balles hors écran
+    // mise à jour de l'animation
+    pub fn update(&mut self) {
         self.bullet_sprite.update();
     }
-
-    // test si une des balles a touché quelque chose
-    pub fn collides_with<F>(&mut self, shape: &mut Shape, collision: &Collision, f: &mut F)
-    where
-        F: FnMut(&mut Shape, &mut Shape, &Collision),
-    {
-        for bullet in self.bullets.iter_mut() {
-            if bullet.collides_with(&shape) {
-                bullet.collided = true;
-                f(shape, bullet, collision); // Appelle la callback pour faire d'autres choses en cas de collision
-            }
-        }
-    }
 }
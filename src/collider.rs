@@ -0,0 +1,659 @@
+/* Structure Collider (formes de collision et tests précis) */
+
+use crate::masks::SheetMasks;
+use macroquad::prelude::*;
+use nanoserde::DeRon;
+use std::rc::Rc;
+
+// forme de collision déclarée dans un fichier de données, en pixels de l'image
+// du sprite, depuis son coin haut gauche
+#[derive(DeRon, Clone, Default)]
+pub enum ColliderDef {
+    // toute l'image (par défaut)
+    #[default]
+    Frame,
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+    },
+    Aabb {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    // boîte tournée de angle degrés autour de son centre (x, y)
+    Obb {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        angle: f32,
+    },
+    // polygone convexe
+    Polygon {
+        points: Vec<(f32, f32)>,
+    },
+    // pixels pleins de l'image affichée (test plus coûteux, réservé aux formes irrégulières)
+    Mask,
+}
+
+// forme de collision à l'écran, relative à la position de l'objet
+#[derive(Clone)]
+pub enum Collider {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Aabb {
+        center: Vec2,
+        half: Vec2,
+    },
+    Obb {
+        center: Vec2,
+        half: Vec2,
+        angle: f32,
+    },
+    Polygon {
+        points: Vec<Vec2>,
+    },
+    // image centrée sur l'objet ; row et frame suivent l'animation
+    Mask {
+        sheet: Rc<SheetMasks>,
+        row: u32,
+        frame: u32,
+        scale: f32,
+    },
+}
+
+// sommets d'une forme convexe à l'écran, sans allocation pour les boîtes
+enum Vertices<'a> {
+    Quad([Vec2; 4]),
+    Points(&'a [Vec2], Vec2),
+}
+
+impl ColliderDef {
+    // vérification des valeurs lues dans un fichier
+    pub fn check(&self) -> Result<(), &'static str> {
+        match self {
+            ColliderDef::Circle { radius, .. } if !radius.is_finite() || *radius <= 0.0 => {
+                Err("collider radius must be greater than 0")
+            }
+            ColliderDef::Aabb { width, height, .. } | ColliderDef::Obb { width, height, .. }
+                if !width.is_finite() || !height.is_finite() || *width <= 0.0 || *height <= 0.0 =>
+            {
+                Err("collider width and height must be greater than 0")
+            }
+            ColliderDef::Polygon { points } if points.len() < 3 => {
+                Err("collider polygon needs at least 3 points")
+            }
+            ColliderDef::Polygon { points } => {
+                let points: Vec<Vec2> = points.iter().map(|&(x, y)| vec2(x, y)).collect();
+                check_convex(&points)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // passage à l'écran : origine au centre de l'image, puis mise à l'échelle du sprite affiché ;
+    // sheet porte les masques de la planche pour la forme Mask
+    pub fn to_collider(
+        &self,
+        frame_width: f32,
+        frame_height: f32,
+        scale: f32,
+        sheet: Option<Rc<SheetMasks>>,
+    ) -> Collider {
+        let origin = vec2(frame_width, frame_height) / 2.0;
+        let place = |x: f32, y: f32| (vec2(x, y) - origin) * scale;
+        if let (ColliderDef::Mask, Some(sheet)) = (self, sheet) {
+            return Collider::Mask {
+                sheet,
+                row: 0,
+                frame: 0,
+                scale,
+            };
+        }
+        match self {
+            // sans masque chargé, toute l'image
+            ColliderDef::Frame | ColliderDef::Mask => Collider::Aabb {
+                center: Vec2::ZERO,
+                half: vec2(frame_width, frame_height) * scale / 2.0,
+            },
+            ColliderDef::Circle { x, y, radius } => Collider::Circle {
+                center: place(*x, *y),
+                radius: radius * scale,
+            },
+            ColliderDef::Aabb {
+                x,
+                y,
+                width,
+                height,
+            } => Collider::Aabb {
+                center: place(x + width / 2.0, y + height / 2.0),
+                half: vec2(*width, *height) * scale / 2.0,
+            },
+            ColliderDef::Obb {
+                x,
+                y,
+                width,
+                height,
+                angle,
+            } => Collider::Obb {
+                center: place(*x, *y),
+                half: vec2(*width, *height) * scale / 2.0,
+                angle: angle.to_radians(),
+            },
+            ColliderDef::Polygon { points } => Collider::Polygon {
+                points: points.iter().map(|&(x, y)| place(x, y)).collect(),
+            },
+        }
+    }
+}
+
+impl Collider {
+    // même forme tournée de angle radians autour de la position de l'objet
+    pub fn rotated(&self, angle: f32) -> Collider {
+        let rotation = Vec2::from_angle(angle);
+        match self {
+            Collider::Circle { center, radius } => Collider::Circle {
+                center: rotation.rotate(*center),
+                radius: *radius,
+            },
+            Collider::Aabb { center, half } => Collider::Obb {
+                center: rotation.rotate(*center),
+                half: *half,
+                angle,
+            },
+            Collider::Obb {
+                center,
+                half,
+                angle: own,
+            } => Collider::Obb {
+                center: rotation.rotate(*center),
+                half: *half,
+                angle: own + angle,
+            },
+            Collider::Polygon { points } => Collider::Polygon {
+                points: points.iter().map(|&point| rotation.rotate(point)).collect(),
+            },
+            // les masques ne tournent pas
+            Collider::Mask { .. } => self.clone(),
+        }
+    }
+
+    // image de l'animation à utiliser pour un masque
+    pub fn set_frame(&mut self, new_row: u32, new_frame: u32) {
+        if let Collider::Mask { row, frame, .. } = self {
+            *row = new_row;
+            *frame = new_frame;
+        }
+    }
+
+    // rectangle englobant à l'écran (pour la grille)
+    pub fn bounds(&self, position: Vec2) -> Rect {
+        let (min, max) = match self {
+            Collider::Circle { center, radius } => (
+                position + *center - Vec2::splat(*radius),
+                position + *center + Vec2::splat(*radius),
+            ),
+            Collider::Aabb { center, half } => {
+                (position + *center - *half, position + *center + *half)
+            }
+            Collider::Mask { sheet, scale, .. } => {
+                let half = vec2(sheet.frame_width as f32, sheet.frame_height as f32) * *scale / 2.0;
+                (position - half, position + half)
+            }
+            _ => {
+                let vertices = self.vertices(position).unwrap();
+                (0..vertices.len()).map(|i| vertices.get(i)).fold(
+                    (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                    |(min, max), point| (min.min(point), max.max(point)),
+                )
+            }
+        };
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    fn vertices(&self, position: Vec2) -> Option<Vertices<'_>> {
+        match self {
+            Collider::Circle { .. } | Collider::Mask { .. } => None,
+            Collider::Aabb { center, half } => {
+                let c = position + *center;
+                Some(Vertices::Quad([
+                    c + vec2(-half.x, -half.y),
+                    c + vec2(half.x, -half.y),
+                    c + vec2(half.x, half.y),
+                    c + vec2(-half.x, half.y),
+                ]))
+            }
+            Collider::Obb {
+                center,
+                half,
+                angle,
+            } => {
+                let c = position + *center;
+                let rotation = Vec2::from_angle(*angle);
+                let corner = |x: f32, y: f32| c + rotation.rotate(vec2(x, y));
+                Some(Vertices::Quad([
+                    corner(-half.x, -half.y),
+                    corner(half.x, -half.y),
+                    corner(half.x, half.y),
+                    corner(-half.x, half.y),
+                ]))
+            }
+            Collider::Polygon { points } => Some(Vertices::Points(points, position)),
+        }
+    }
+
+    // test précis entre deux formes placées à l'écran ; le résultat ne dépend pas de l'ordre
+    pub fn overlaps(&self, position: Vec2, other: &Collider, other_position: Vec2) -> bool {
+        match (self, other) {
+            (Collider::Mask { .. }, _) => mask_overlaps(self, position, other, other_position),
+            (_, Collider::Mask { .. }) => mask_overlaps(other, other_position, self, position),
+            (
+                Collider::Circle { center, radius },
+                Collider::Circle {
+                    center: other_center,
+                    radius: other_radius,
+                },
+            ) => {
+                (position + *center).distance_squared(other_position + *other_center)
+                    <= (radius + other_radius).powi(2)
+            }
+            (Collider::Circle { center, radius }, _) => circle_overlaps_convex(
+                position + *center,
+                *radius,
+                &other.vertices(other_position).unwrap(),
+            ),
+            (_, Collider::Circle { center, radius }) => circle_overlaps_convex(
+                other_position + *center,
+                *radius,
+                &self.vertices(position).unwrap(),
+            ),
+            (
+                Collider::Aabb { center, half },
+                Collider::Aabb {
+                    center: other_center,
+                    half: other_half,
+                },
+            ) => {
+                let distance = ((position + *center) - (other_position + *other_center)).abs();
+                distance.x <= half.x + other_half.x && distance.y <= half.y + other_half.y
+            }
+            _ => convex_overlaps_convex(
+                &self.vertices(position).unwrap(),
+                &other.vertices(other_position).unwrap(),
+            ),
+        }
+    }
+}
+
+// point de l'écran à l'intérieur de la forme
+fn contains(collider: &Collider, position: Vec2, point: Vec2) -> bool {
+    match collider {
+        Collider::Circle { center, radius } => {
+            point.distance_squared(position + *center) <= radius * radius
+        }
+        Collider::Mask {
+            sheet,
+            row,
+            frame,
+            scale,
+        } => {
+            let size = vec2(sheet.frame_width as f32, sheet.frame_height as f32) * *scale;
+            let local = (point - (position - size / 2.0)) / *scale;
+            match sheet.get(*row, *frame) {
+                Some(mask) => mask.is_solid(local.x.floor() as i32, local.y.floor() as i32),
+                // image absente de la planche : toute l'image, comme la forme Frame
+                None => {
+                    local.x >= 0.0
+                        && local.y >= 0.0
+                        && local.x < sheet.frame_width as f32
+                        && local.y < sheet.frame_height as f32
+                }
+            }
+        }
+        _ => {
+            // du même côté de toutes les arêtes
+            let vertices = collider.vertices(position).unwrap();
+            let (mut before, mut after) = (false, false);
+            for i in 0..vertices.len() {
+                let side = vertices.normal(i).dot(point - vertices.get(i));
+                before |= side < 0.0;
+                after |= side > 0.0;
+                if before && after {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
+
+// un pixel plein du masque dans l'autre forme ; on parcourt les pixels de l'écran
+// communs aux deux rectangles englobants
+fn mask_overlaps(mask: &Collider, position: Vec2, other: &Collider, other_position: Vec2) -> bool {
+    let a = mask.bounds(position);
+    let b = other.bounds(other_position);
+    let Some(common) = a.intersect(b) else {
+        return false;
+    };
+    let (x0, x1) = (common.x.floor() as i32, (common.x + common.w).ceil() as i32);
+    let (y0, y1) = (common.y.floor() as i32, (common.y + common.h).ceil() as i32);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let point = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            if contains(mask, position, point) && contains(other, other_position, point) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+impl Vertices<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Vertices::Quad(_) => 4,
+            Vertices::Points(points, _) => points.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> Vec2 {
+        match self {
+            Vertices::Quad(points) => points[i],
+            Vertices::Points(points, position) => points[i] + *position,
+        }
+    }
+
+    // normale de l'arête qui part du sommet i
+    fn normal(&self, i: usize) -> Vec2 {
+        let edge = self.get((i + 1) % self.len()) - self.get(i);
+        vec2(-edge.y, edge.x)
+    }
+
+    // étendue de la forme projetée sur un axe
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        (0..self.len())
+            .map(|i| self.get(i).dot(axis))
+            .fold((f32::MAX, f32::MIN), |(min, max), value| {
+                (min.min(value), max.max(value))
+            })
+    }
+}
+
+// les sommets tournent toujours dans le même sens et font un seul tour : un polygone
+// plat (points alignés) ou étoilé (qui se croise) fausserait l'axe séparateur
+fn check_convex(points: &[Vec2]) -> Result<(), &'static str> {
+    let count = points.len();
+    let turns: Vec<(f32, f32)> = (0..count)
+        .map(|i| {
+            let a = points[(i + 1) % count] - points[i];
+            let b = points[(i + 2) % count] - points[(i + 1) % count];
+            (a.perp_dot(b), a.dot(b))
+        })
+        .collect();
+    if turns.iter().all(|&(cross, _)| cross == 0.0) {
+        return Err("collider polygon points must not all lie on a line");
+    }
+    if !turns.iter().all(|&(cross, _)| cross >= 0.0)
+        && !turns.iter().all(|&(cross, _)| cross <= 0.0)
+    {
+        return Err("collider polygon must be convex");
+    }
+    // somme des angles de virage : un tour complet, deux pour un pentagramme
+    let total: f32 = turns.iter().map(|&(cross, dot)| cross.atan2(dot)).sum();
+    if (total.abs() - std::f32::consts::TAU).abs() > 0.01 {
+        return Err("collider polygon must not cross itself");
+    }
+    Ok(())
+}
+
+// théorème de l'axe séparateur : on cherche un axe où les deux projections ne se touchent pas
+fn convex_overlaps_convex(a: &Vertices, b: &Vertices) -> bool {
+    let axes = (0..a.len())
+        .map(|i| a.normal(i))
+        .chain((0..b.len()).map(|i| b.normal(i)));
+    for axis in axes {
+        let (a_min, a_max) = a.project(axis);
+        let (b_min, b_max) = b.project(axis);
+        if a_max < b_min || b_max < a_min {
+            return false;
+        }
+    }
+    true
+}
+
+// mêmes axes que le polygone, plus celui qui va du centre du cercle au sommet le plus proche
+fn circle_overlaps_convex(center: Vec2, radius: f32, shape: &Vertices) -> bool {
+    let closest = (0..shape.len())
+        .map(|i| shape.get(i))
+        .min_by(|a, b| {
+            a.distance_squared(center)
+                .total_cmp(&b.distance_squared(center))
+        })
+        .unwrap();
+    let axes = (0..shape.len())
+        .map(|i| shape.normal(i))
+        .chain(std::iter::once(closest - center));
+    for axis in axes {
+        let axis = axis.normalize_or_zero();
+        if axis == Vec2::ZERO {
+            continue;
+        }
+        let (min, max) = shape.project(axis);
+        let projected = center.dot(axis);
+        if projected + radius < min || max < projected - radius {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(radius: f32) -> Collider {
+        Collider::Circle {
+            center: Vec2::ZERO,
+            radius,
+        }
+    }
+
+    fn aabb(half: f32) -> Collider {
+        Collider::Aabb {
+            center: Vec2::ZERO,
+            half: Vec2::splat(half),
+        }
+    }
+
+    // carré de demi-côté 1 tourné de 45° : |x| + |y| <= √2
+    fn diamond() -> Collider {
+        Collider::Obb {
+            center: Vec2::ZERO,
+            half: Vec2::ONE,
+            angle: std::f32::consts::FRAC_PI_4,
+        }
+    }
+
+    // triangle rectangle x >= 0, y >= 0, x + y <= 4 (ou son opposé)
+    fn triangle(side: f32) -> Collider {
+        Collider::Polygon {
+            points: vec![Vec2::ZERO, vec2(side, 0.0), vec2(0.0, side)],
+        }
+    }
+
+    // image 4x4 dont seule la moitié gauche est pleine : pixels de x = -2 à 0 autour de l'objet
+    fn half_mask() -> Collider {
+        let bytes = (0..16)
+            .flat_map(|i| {
+                if i % 4 < 2 {
+                    [255, 255, 255, 255]
+                } else {
+                    [0, 0, 0, 0]
+                }
+            })
+            .collect();
+        let image = Image {
+            bytes,
+            width: 4,
+            height: 4,
+        };
+        Collider::Mask {
+            sheet: Rc::new(SheetMasks::from_image(&image, 4, 4)),
+            row: 0,
+            frame: 0,
+            scale: 1.0,
+        }
+    }
+
+    // a à l'origine, b décalé de offset ; on vérifie au passage l'ordre inverse
+    fn overlaps(a: &Collider, b: &Collider, offset: Vec2) -> bool {
+        let result = a.overlaps(Vec2::ZERO, b, offset);
+        assert_eq!(result, b.overlaps(offset, a, Vec2::ZERO));
+        result
+    }
+
+    #[test]
+    fn circle_against_circle() {
+        assert!(overlaps(&circle(1.0), &circle(1.0), vec2(1.9, 0.0)));
+        assert!(!overlaps(&circle(1.0), &circle(1.0), vec2(1.5, 1.5)));
+    }
+
+    #[test]
+    fn circle_against_aabb_corner() {
+        // les rectangles englobants se touchent, mais le coin est hors du cercle
+        assert!(!overlaps(&aabb(1.0), &circle(1.0), vec2(1.8, 1.8)));
+        assert!(overlaps(&aabb(1.0), &circle(1.2), vec2(1.8, 1.8)));
+    }
+
+    #[test]
+    fn circle_against_obb() {
+        assert!(!overlaps(&diamond(), &circle(0.5), vec2(2.0, 0.0)));
+        assert!(overlaps(&diamond(), &circle(0.7), vec2(2.0, 0.0)));
+    }
+
+    #[test]
+    fn circle_against_polygon() {
+        assert!(!overlaps(&triangle(4.0), &circle(0.5), vec2(3.0, 3.0)));
+        assert!(overlaps(&triangle(4.0), &circle(1.5), vec2(3.0, 3.0)));
+    }
+
+    #[test]
+    fn aabb_against_aabb() {
+        assert!(overlaps(&aabb(1.0), &aabb(0.5), vec2(1.4, -1.4)));
+        assert!(!overlaps(&aabb(1.0), &aabb(0.5), vec2(1.6, 0.0)));
+    }
+
+    #[test]
+    fn aabb_against_obb() {
+        assert!(overlaps(&diamond(), &aabb(0.5), vec2(1.8, 0.0)));
+        assert!(!overlaps(&diamond(), &aabb(0.5), vec2(1.8, 1.0)));
+    }
+
+    #[test]
+    fn obb_against_obb() {
+        assert!(overlaps(&diamond(), &diamond(), vec2(2.7, 0.0)));
+        assert!(!overlaps(&diamond(), &diamond(), vec2(2.9, 0.0)));
+    }
+
+    #[test]
+    fn polygon_against_polygon() {
+        assert!(overlaps(&triangle(4.0), &triangle(-4.0), vec2(3.9, 3.9)));
+        assert!(!overlaps(&triangle(4.0), &triangle(-4.0), vec2(4.1, 4.1)));
+    }
+
+    #[test]
+    fn mask_uses_only_solid_pixels() {
+        let mask = half_mask();
+        assert!(overlaps(&mask, &aabb(0.6), vec2(-1.0, 0.0)));
+        assert!(!overlaps(&mask, &aabb(0.6), vec2(1.0, 0.0)));
+        assert!(overlaps(&mask, &circle(0.8), vec2(-1.0, 1.0)));
+        assert!(!overlaps(&mask, &circle(0.8), vec2(1.2, 0.0)));
+        assert!(overlaps(&mask, &triangle(2.0), vec2(-2.0, -2.0)));
+        assert!(!overlaps(&mask, &triangle(2.0), vec2(0.2, -1.5)));
+        assert!(overlaps(&mask, &mask, vec2(1.0, 0.0)));
+        assert!(!overlaps(&mask, &mask, vec2(2.0, 0.0)));
+    }
+
+    #[test]
+    fn missing_mask_frame_uses_the_whole_frame() {
+        let mut mask = half_mask();
+        mask.set_frame(0, 3);
+        // la moitié droite, vide dans le masque, compte comme pleine
+        assert!(overlaps(&mask, &aabb(0.6), vec2(1.0, 0.0)));
+        assert!(!overlaps(&mask, &aabb(0.6), vec2(2.7, 0.0)));
+        mask.set_frame(2, 0);
+        assert!(overlaps(&mask, &aabb(0.6), vec2(1.0, 0.0)));
+    }
+
+    #[test]
+    fn result_does_not_depend_on_argument_order() {
+        let shapes = [
+            circle(1.0),
+            aabb(1.0),
+            diamond(),
+            triangle(3.0),
+            triangle(-3.0),
+            half_mask(),
+        ];
+        for a in &shapes {
+            for b in &shapes {
+                for y in -8..=8 {
+                    for x in -8..=8 {
+                        let offset = vec2(x as f32, y as f32) * 0.4;
+                        assert_eq!(
+                            a.overlaps(Vec2::ZERO, b, offset),
+                            b.overlaps(offset, a, Vec2::ZERO)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn polygon(points: &[(f32, f32)]) -> Result<(), &'static str> {
+        ColliderDef::Polygon {
+            points: points.to_vec(),
+        }
+        .check()
+    }
+
+    #[test]
+    fn convex_polygons_are_accepted_in_both_directions() {
+        let square = [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)];
+        assert_eq!(polygon(&square), Ok(()));
+        let reversed: Vec<_> = square.iter().rev().copied().collect();
+        assert_eq!(polygon(&reversed), Ok(()));
+    }
+
+    #[test]
+    fn bad_polygons_are_rejected() {
+        assert_eq!(
+            polygon(&[(0.0, 0.0), (1.0, 0.0)]),
+            Err("collider polygon needs at least 3 points")
+        );
+        assert_eq!(
+            polygon(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]),
+            Err("collider polygon points must not all lie on a line")
+        );
+        assert_eq!(
+            polygon(&[(0.0, 0.0), (4.0, 0.0), (1.0, 1.0), (0.0, 4.0)]),
+            Err("collider polygon must be convex")
+        );
+        // pentagramme : on tourne toujours du même côté, mais deux fois
+        let star: Vec<_> = (0..5)
+            .map(|i| {
+                let point = Vec2::from_angle(i as f32 * 4.0 * std::f32::consts::PI / 5.0) * 10.0;
+                (point.x, point.y)
+            })
+            .collect();
+        assert_eq!(
+            polygon(&star),
+            Err("collider polygon must not cross itself")
+        );
+    }
+}
@@ -0,0 +1,175 @@
+/* Structure ControlsScreen (écran de configuration des touches) */
+
+use crate::gamepad::GamepadButton;
+use crate::input::*;
+use crate::settings::Settings;
+use macroquad::prelude::*;
+
+pub struct ControlsScreen {
+    selected: usize,
+    waiting: bool,
+}
+
+impl ControlsScreen {
+    pub fn new() -> ControlsScreen {
+        ControlsScreen {
+            selected: 0,
+            waiting: false,
+        }
+    }
+
+    // les touches de cet écran sont fixes pour ne jamais se retrouver bloqué
+    // retourne true quand on quitte l'écran
+    pub fn update(&mut self, settings: &mut Settings, backend: &dyn InputBackend) -> bool {
+        let map = &mut settings.controls;
+        let action = Action::ALL[self.selected];
+        let pad = backend.gamepad();
+        let back = backend.key_pressed(KeyCode::Escape) || pad.is_pressed(GamepadButton::East);
+        if self.waiting {
+            // Escape et East annulent l'ajout au lieu d'être associés à l'action
+            if back {
+                self.waiting = false;
+                return false;
+            }
+            let key = BINDABLE_KEYS
+                .into_iter()
+                .find(|key| backend.key_pressed(*key))
+                .map(Binding::Key);
+            let button = GamepadButton::ALL
+                .into_iter()
+                .find(|button| backend.gamepad().is_pressed(*button))
+                .map(Binding::Gamepad);
+            let mouse = BINDABLE_MOUSE_BUTTONS
+                .into_iter()
+                .find(|button| backend.mouse_pressed(*button))
+                .map(Binding::Mouse);
+            if let Some(binding) = key.or(button).or(mouse) {
+                map.bind(action, binding);
+                self.waiting = false;
+            }
+            return false;
+        }
+        if backend.key_pressed(KeyCode::Up) || pad.is_pressed(GamepadButton::DpadUp) {
+            self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len();
+        }
+        if backend.key_pressed(KeyCode::Down) || pad.is_pressed(GamepadButton::DpadDown) {
+            self.selected = (self.selected + 1) % Action::ALL.len();
+        }
+        if backend.key_pressed(KeyCode::Enter) || pad.is_pressed(GamepadButton::South) {
+            self.waiting = true;
+        }
+        if backend.key_pressed(KeyCode::Delete) || backend.key_pressed(KeyCode::Backspace) {
+            map.clear(action);
+        }
+        if backend.key_pressed(KeyCode::F5) {
+            *map = InputMap::new();
+            settings.mouse_aim = false;
+        }
+        if backend.key_pressed(KeyCode::F6) {
+            settings.mouse_aim = !settings.mouse_aim;
+        }
+        if back {
+            settings.save();
+            self.waiting = false;
+            return true;
+        }
+        false
+    }
+
+    pub fn display(&self, settings: &Settings, backend: &dyn InputBackend) {
+        let map = &settings.controls;
+        let text = "Controls";
+        let text_dimensions = measure_text(text, None, 50, 1.0);
+        draw_text(
+            text,
+            screen_width() / 2.0 - text_dimensions.width / 2.0,
+            100.0,
+            50.0,
+            YELLOW,
+        );
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let y = 180.0 + i as f32 * 40.0;
+            let color = if i == self.selected { YELLOW } else { WHITE };
+            let keys = if i == self.selected && self.waiting {
+                "press a key... (Escape: cancel)".to_string()
+            } else {
+                let names: Vec<String> = map.get(action).iter().map(Binding::name).collect();
+                names.join(", ")
+            };
+            if i == self.selected {
+                draw_text(">", 60.0, y, 30.0, color);
+            }
+            draw_text(action.name(), 90.0, y, 30.0, color);
+            draw_text(&keys, 300.0, y, 24.0, color);
+        }
+        let gamepad = if backend.gamepad().connected {
+            "Gamepad connected"
+        } else {
+            "No gamepad detected"
+        };
+        draw_text(gamepad, 20.0, screen_height() - 60.0, 18.0, GRAY);
+        let aim = if settings.mouse_aim {
+            "Aim: toward the mouse cursor (F6 to change)"
+        } else {
+            "Aim: straight up (F6 to change)"
+        };
+        draw_text(aim, 20.0, screen_height() - 90.0, 24.0, WHITE);
+        draw_text(
+            "Up/Down: select  Enter: add key or button  Backspace: clear  F5: defaults  Escape: back",
+            20.0,
+            screen_height() - 30.0,
+            18.0,
+            WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::FakeBackend;
+
+    // écran en attente d'une touche pour l'action Fire
+    fn waiting_for_fire() -> ControlsScreen {
+        ControlsScreen {
+            selected: Action::ALL.iter().position(|a| *a == Action::Fire).unwrap(),
+            waiting: true,
+        }
+    }
+
+    #[test]
+    fn escape_or_east_cancels_a_rebind() {
+        let mut settings = Settings::new();
+        let before = settings.controls.get(Action::Fire).to_vec();
+        let mut screen = waiting_for_fire();
+        let mut backend = FakeBackend::default();
+        backend.keys.push(KeyCode::Escape);
+        // on reste sur l'écran, sans rien associer
+        assert!(!screen.update(&mut settings, &backend));
+        assert!(!screen.waiting);
+        assert_eq!(settings.controls.get(Action::Fire), before);
+
+        let mut screen = waiting_for_fire();
+        let mut backend = FakeBackend::default();
+        backend.hold(&[GamepadButton::East]);
+        assert!(!screen.update(&mut settings, &backend));
+        assert!(!screen.waiting);
+        assert_eq!(settings.controls.get(Action::Fire), before);
+    }
+
+    #[test]
+    fn next_key_is_bound_while_waiting() {
+        let mut settings = Settings::new();
+        let mut screen = waiting_for_fire();
+        let mut backend = FakeBackend::default();
+        backend.keys.push(KeyCode::K);
+        assert!(!screen.update(&mut settings, &backend));
+        assert!(!screen.waiting);
+        assert!(
+            settings
+                .controls
+                .get(Action::Fire)
+                .contains(&Binding::Key(KeyCode::K))
+        );
+    }
+}
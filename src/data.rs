@@ -0,0 +1,75 @@
+/* Structure DataError (erreurs de lecture des fichiers de données) */
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(String, String),
+    Parse(String, nanoserde::DeRonErr),
+    Invalid {
+        file: String,
+        entry: usize,
+        name: String,
+        message: String,
+    },
+    Empty(String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io(file, message) => write!(f, "{}: {}", file, message),
+            DataError::Parse(file, e) => write!(
+                f,
+                "{}: syntax error line {} column {}: {}",
+                file,
+                e.line + 1,
+                e.col + 1,
+                e.msg
+            ),
+            DataError::Invalid {
+                file,
+                entry,
+                name,
+                message,
+            } => write!(
+                f,
+                "{}: entry #{} \"{}\": {}",
+                file,
+                entry + 1,
+                name,
+                message
+            ),
+            DataError::Empty(file) => write!(f, "{}: the file has no entry", file),
+        }
+    }
+}
+
+// lecture d'un fichier texte du dossier assets
+pub async fn load_data_file(file: &str) -> Result<String, DataError> {
+    macroquad::file::load_string(file)
+        .await
+        .map_err(|e| DataError::Io(file.to_string(), e.to_string()))
+}
+
+// lecture d'un fichier de données avec repli : s'il est absent ou invalide, on garde
+// la version livrée avec le jeu (copie intégrée au binaire) et on rend l'erreur
+pub async fn load_or_default<T>(
+    file: &str,
+    parse: impl FnOnce(&str) -> Result<T, DataError>,
+    default: impl FnOnce() -> T,
+) -> (T, Option<DataError>) {
+    parse_or_default(load_data_file(file).await, parse, default)
+}
+
+// repli sur la version par défaut d'un fichier déjà lu (ou illisible)
+pub fn parse_or_default<T>(
+    text: Result<String, DataError>,
+    parse: impl FnOnce(&str) -> Result<T, DataError>,
+    default: impl FnOnce() -> T,
+) -> (T, Option<DataError>) {
+    match text.and_then(|text| parse(&text)) {
+        Ok(value) => (value, None),
+        Err(e) => (default(), Some(e)),
+    }
+}
@@ -1,158 +1,177 @@
 /* Structure EnnemiesSet (gestion des ennemis) */
 
-use crate::{Collision, Shape};
+use crate::Shape;
+use crate::archetypes::*;
+use crate::assets::Assets;
+use crate::atlas::AtlasSprite;
+use crate::masks::MaskLibrary;
+use crate::timestep::ANIMATION_FPS;
 use macroquad::experimental::animation::{AnimatedSprite, Animation};
 use macroquad::prelude::*;
 
-const MAX_ENEMIES_NUMBER: u16 = 50;
+// vitesse horizontale des ennemis en zigzag, relative à leur vitesse de chute
+const ZIGZAG_RATIO: f32 = 0.8;
+// accélération des ennemis qui plongent, en pixels par seconde²
+const DIVE_ACCELERATION: f32 = 120.0;
 
 pub struct EnemiesSet {
     pub enemies: Vec<Shape>,
-    enemy_small_sprite: AnimatedSprite,
-    enemy_medium_sprite: AnimatedSprite,
-    enemy_big_sprite: AnimatedSprite,
-    enemy_small_texture: Texture2D,
-    enemy_medium_texture: Texture2D,
-    enemy_big_texture: Texture2D,
-    number: u16,
+    pub archetypes: Archetypes,
+    masks: MaskLibrary,
+    // horloge des animations
+    time: f32,
 }
 
-impl EnemiesSet {
-    pub async fn new() -> EnemiesSet {
-        let enemy_small_sprite = AnimatedSprite::new(
-            17,
-            16,
-            &[Animation {
-                name: "enemy_small".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            }],
-            true,
-        );
-        let enemy_medium_sprite = AnimatedSprite::new(
-            32,
-            16,
-            &[Animation {
-                name: "enemy_medium".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            }],
-            true,
-        );
-        let enemy_big_sprite = AnimatedSprite::new(
-            32,
-            32,
-            &[Animation {
-                name: "enemy_big".to_string(),
-                row: 0,
-                frames: 2,
-                fps: 12,
-            }],
-            true,
-        );
-        let enemy_small_texture: Texture2D = load_texture("enemy-small.png")
-            .await
-            .expect("Couldn't load file");
-        enemy_small_texture.set_filter(FilterMode::Nearest);
-
-        let enemy_medium_texture: Texture2D = load_texture("enemy-medium.png")
-            .await
-            .expect("Couldn't load file");
-        enemy_medium_texture.set_filter(FilterMode::Nearest);
+// ressources d'affichage des ennemis (une texture et une animation par type)
+pub struct EnemiesView {
+    sprites: Vec<AnimatedSprite>,
+    textures: Vec<AtlasSprite>,
+}
 
-        let enemy_big_texture: Texture2D = load_texture("enemy-big.png")
-            .await
-            .expect("Couldn't load file");
-        enemy_big_texture.set_filter(FilterMode::Nearest);
-        let number = 0;
+impl EnemiesSet {
+    pub fn new(archetypes: Archetypes, masks: MaskLibrary) -> EnemiesSet {
         EnemiesSet {
             enemies: vec![],
-            enemy_small_texture,
-            enemy_medium_texture,
-            enemy_big_texture,
-            enemy_small_sprite,
-            enemy_medium_sprite,
-            enemy_big_sprite,
-            number,
-        }
-    }
-
-    pub fn display(&self) {
-        for enemy in &self.enemies {
-            let (texture, frame) = match enemy.size {
-                _ if enemy.size <= 32.0 => {
-                    (&self.enemy_small_texture, self.enemy_small_sprite.frame())
-                }
-                33.0..=48.0 => (&self.enemy_medium_texture, self.enemy_medium_sprite.frame()),
-                _ => (&self.enemy_big_texture, self.enemy_big_sprite.frame()),
-            };
-            draw_texture_ex(
-                &texture,
-                enemy.x - enemy.size / 2.0,
-                enemy.y - enemy.size / 2.0,
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(enemy.size, enemy.size)),
-                    source: Some(frame.source_rect),
-                    ..Default::default()
-                },
-            );
+            archetypes,
+            masks,
+            time: 0.0,
         }
     }
 
     // suppression des ennemis
     pub fn clear(&mut self) {
         self.enemies.clear();
-        self.number=0;
+        self.time = 0.0;
+    }
+
+    // types d'ennemis modifiés en cours de partie : on retire ceux dont le type n'existe plus
+    pub fn reload(&mut self, archetypes: Archetypes) {
+        let kinds = archetypes.list.len();
+        self.enemies.retain(|enemy| enemy.kind < kinds);
+        self.archetypes = archetypes;
+    }
 
+    // les ennemis déjà en jeu gardent leurs anciens masques
+    pub fn set_masks(&mut self, masks: MaskLibrary) {
+        self.masks = masks;
+    }
+
+    // image de l'animation d'un type d'ennemi
+    pub fn frame(&self, kind: usize) -> u32 {
+        (self.time * ANIMATION_FPS) as u32 % self.archetypes.get(kind).def.frames
+    }
+
+    // ajout d'un ennemi en haut de l'écran ; speed_factor accélère les niveaux suivants
+    pub fn spawn(&mut self, kind: usize, speed_factor: f32, width: f32, rng: &rand::RandGenerator) {
+        let archetype = self.archetypes.get(kind);
+        let def = &archetype.def;
+        let size = rng.gen_range(def.size_min, def.size_max);
+        let speed = rng.gen_range(def.speed_min, def.speed_max) * speed_factor;
+        let x = rng.gen_range(size / 2.0, width - size / 2.0);
+        // l'image est agrandie sans déformation, son plus grand côté mesurant size
+        let (frame_width, frame_height) = (def.width as f32, def.height as f32);
+        let collider = def.collider.to_collider(
+            frame_width,
+            frame_height,
+            size / frame_width.max(frame_height),
+            self.masks.get(&def.texture),
+        );
+        let velocity = match archetype.behaviour {
+            Behaviour::Zigzag if rng.gen_range(0, 2) == 0 => vec2(-speed * ZIGZAG_RATIO, 0.0),
+            Behaviour::Zigzag => vec2(speed * ZIGZAG_RATIO, 0.0),
+            _ => Vec2::ZERO,
+        };
+        self.enemies.push(Shape {
+            size,
+            speed,
+            x,
+            y: -size,
+            collided: false,
+            life: def.life,
+            prev_x: x,
+            prev_y: -size,
+            velocity,
+            kind,
+            collider,
+        });
     }
 
     // mise à jour de la positions des ennemis
-    pub fn update(&mut self, delta_time: f32) {
-        // ajout des ennemies : 5% de chance d'avoir un nouvel ennemie
-        if self.number < MAX_ENEMIES_NUMBER {
-            if rand::gen_range(0, 99) >= 95 {
-                let size = rand::gen_range(16.0, 64.0);
-                self.number+=1;
-                self.enemies.push(Shape {
-                    size,
-                    speed: rand::gen_range(50.0, 150.0),
-                    x: rand::gen_range(size / 2.0, screen_width() - size / 2.0),
-                    y: -size,
-                    collided: false,
-                    life: 0,
-                });
-            }
-        }
-        // on les fait tomber
+    pub fn update(&mut self, delta_time: f32, width: f32, height: f32) {
+        self.time += delta_time;
+        let frames: Vec<u32> = (0..self.archetypes.list.len())
+            .map(|kind| self.frame(kind))
+            .collect();
+        // on les fait tomber, chacun à sa façon
         for enemy in &mut self.enemies {
+            match self.archetypes.get(enemy.kind).behaviour {
+                Behaviour::Straight => {}
+                Behaviour::Zigzag => {
+                    // rebond sur les bords de l'écran
+                    enemy.x += enemy.velocity.x * delta_time;
+                    if enemy.x < enemy.size / 2.0 {
+                        enemy.velocity.x = enemy.velocity.x.abs();
+                    } else if enemy.x > width - enemy.size / 2.0 {
+                        enemy.velocity.x = -enemy.velocity.x.abs();
+                    }
+                }
+                Behaviour::Dive => enemy.speed += DIVE_ACCELERATION * delta_time,
+            }
             enemy.y += enemy.speed * delta_time;
+            enemy.collider.set_frame(0, frames[enemy.kind]);
         }
-        self.enemies
-            .retain(|enemy| enemy.y < screen_height() + enemy.size); // on vire les ennemis hors écran
+        self.enemies.retain(|enemy| enemy.y < height + enemy.size); // on vire les ennemis hors écran
         self.enemies.retain(|enemy| !enemy.collided); // on vire les ennemies touchés
-        self.enemy_small_sprite.update();
     }
+}
 
-    pub fn get_list(&mut self) -> &mut Vec<Shape> {
-        &mut self.enemies
+impl EnemiesView {
+    pub fn new(archetypes: &Archetypes, assets: &Assets) -> EnemiesView {
+        let mut sprites = vec![];
+        let mut textures = vec![];
+        for archetype in &archetypes.list {
+            let def = &archetype.def;
+            sprites.push(AnimatedSprite::new(
+                def.width,
+                def.height,
+                &[Animation {
+                    name: def.name.clone(),
+                    row: 0,
+                    frames: def.frames,
+                    fps: ANIMATION_FPS as u32,
+                }],
+                true,
+            ));
+            let texture = assets.sprite(&def.texture);
+            textures.push(texture);
+        }
+        EnemiesView { sprites, textures }
     }
 
-    pub fn collides_with<F>(&mut self, shape: &mut Shape, collision: &Collision, f: &mut F)
-    where
-        F: FnMut(&mut Shape, &mut Shape, &Collision),
-    {
-        for enemy in self.enemies.iter_mut() {
-            if enemy.collides_with(&shape) {
-                enemy.collided = true;
-                f(enemy, shape, collision); // Appelle la callback pour faire d'autres choses en cas de collision
-            }
+    pub fn display(&self, enemies: &EnemiesSet, alpha: f32) {
+        for enemy in &enemies.enemies {
+            let texture = &self.textures[enemy.kind];
+            let frame = self.sprites[enemy.kind].frame();
+            let position = enemy.lerp_position(alpha);
+            // sans déformation : le plus grand côté de l'image mesure size
+            let dest_size = frame.dest_size * enemy.size / frame.dest_size.max_element();
+            texture.draw(
+                position.x - dest_size.x / 2.0,
+                position.y - dest_size.y / 2.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(dest_size),
+                    source: Some(frame.source_rect),
+                    ..Default::default()
+                },
+            );
         }
     }
 
-    pub fn all_destroyed(&mut self) -> bool {
-        self.number==MAX_ENEMIES_NUMBER && self.enemies.len()==0
+    // mise à jour des animations, à l'image calculée par la simulation
+    pub fn update(&mut self, enemies: &EnemiesSet) {
+        for (kind, sprite) in self.sprites.iter_mut().enumerate() {
+            sprite.set_frame(enemies.frame(kind));
+        }
     }
 }
@@ -0,0 +1,61 @@
+/* Structure EventBus (événements de jeu émis par la simulation) */
+
+use crate::Shape;
+use crate::waves::WaveSummary;
+
+// ce qui s'est passé pendant un pas de simulation
+#[derive(Clone)]
+pub enum GameEvent {
+    // x : position du vaisseau, pour placer le son en stéréo
+    ShotFired { x: f32 },
+    // dernier tir avant d'être à court de munitions
+    AmmoEmpty,
+    EnemyDestroyed { enemy: Shape, points: u32 },
+    // un ennemi s'est écrasé sur un bâtiment
+    BuildingDamaged { enemy: Shape },
+    BuildingDestroyed { name: String },
+    // lives : vies restantes après le choc
+    ShipHit { lives: i32, enemy: Shape },
+    WaveCleared(WaveSummary),
+    GameOver,
+    Won,
+}
+
+// un système qui réagit aux événements (score, son, particules, HUD, succès...)
+pub trait Subscriber {
+    fn notify(&mut self, event: &GameEvent);
+}
+
+// file des événements d'un pas, distribués ensuite aux abonnés
+pub struct EventBus {
+    events: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus { events: vec![] }
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    // distribue les événements en attente sans les retirer de la file
+    pub fn dispatch(&self, subscribers: &mut [&mut dyn Subscriber]) {
+        publish(&self.events, subscribers);
+    }
+
+    // vide la file : les événements repartent vers la boucle principale
+    pub fn take(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// chaque abonné reçoit tous les événements, dans l'ordre où ils ont été émis
+pub fn publish(events: &[GameEvent], subscribers: &mut [&mut dyn Subscriber]) {
+    for event in events {
+        for subscriber in subscribers.iter_mut() {
+            subscriber.notify(event);
+        }
+    }
+}
@@ -1,7 +1,8 @@
 /* Structure BulletSet (gestion des balles) */
 
 use crate::Shape;
-use macroquad::audio::{Sound, load_sound, play_sound_once};
+use crate::assets::Assets;
+use crate::events::*;
 use macroquad::prelude::*;
 use macroquad_particles::{self as particles, AtlasConfig};
 use macroquad_particles::{Emitter, EmitterConfig};
@@ -9,20 +10,15 @@ use macroquad_particles::{Emitter, EmitterConfig};
 pub struct ExplosionsSet {
     pub explosions: Vec<(Emitter, Vec2)>,
     pub explosion_texture: Texture2D,
-    pub sound_explosion: Sound,
 }
 
 impl ExplosionsSet {
-    pub async fn new() -> ExplosionsSet {
-        let explosion_texture: Texture2D = load_texture("explosion.png")
-            .await
-            .expect("Couldn't load file");
+    pub fn new(assets: &Assets) -> ExplosionsSet {
+        let explosion_texture = assets.texture("explosion.png");
         explosion_texture.set_filter(FilterMode::Nearest);
-        let sound_explosion = load_sound("explosion.wav").await.unwrap();
         ExplosionsSet {
             explosions: vec![],
             explosion_texture,
-            sound_explosion,
         }
     }
 
@@ -33,7 +29,6 @@ impl ExplosionsSet {
 
     // Ajout d'une explosion
     pub fn push(&mut self, shape: &Shape) {
-        
         self.explosions.push((
             Emitter::new(EmitterConfig {
                 amount: shape.size.round() as u32 * 4,
@@ -42,7 +37,6 @@ impl ExplosionsSet {
             }),
             vec2(shape.x, shape.y),
         ));
-        play_sound_once(&self.sound_explosion); // le son de l'explosion
     }
 
     // affichage des explosions
@@ -77,3 +71,15 @@ impl ExplosionsSet {
         }
     }
 }
+
+// une explosion à la place des ennemis détruits ou écrasés
+impl Subscriber for ExplosionsSet {
+    fn notify(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyDestroyed { enemy, .. }
+            | GameEvent::BuildingDamaged { enemy }
+            | GameEvent::ShipHit { enemy, .. } => self.push(enemy),
+            _ => {}
+        }
+    }
+}
@@ -24,16 +24,14 @@ impl FiresSet {
             }],
             true,
         );
-       fire_sprite.set_animation(0);
-        let fire_texture: Texture2D = load_texture("fire.png")
-            .await
-            .expect("Couldn't load file");
+        fire_sprite.set_animation(0);
+        let fire_texture: Texture2D = load_texture("fire.png").await.expect("Couldn't load file");
         fire_texture.set_filter(FilterMode::Nearest);
 
         FiresSet {
             fires: vec![],
             fire_texture,
-            fire_sprite
+            fire_sprite,
         }
     }
 
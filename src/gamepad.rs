@@ -0,0 +1,233 @@
+/* Structure GamepadState (état de la manette) et GamepadReader (lecture de la vraie manette) */
+
+use macroquad::prelude::*;
+
+// zone morte du stick : en dessous on considère qu'il est au repos
+const DEAD_ZONE: f32 = 0.2;
+
+// numérotation du "standard gamepad" des navigateurs (reprise pour gilrs en natif)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GamepadButton {
+    South = 0,
+    East = 1,
+    West = 2,
+    North = 3,
+    LeftShoulder = 4,
+    RightShoulder = 5,
+    LeftTrigger = 6,
+    RightTrigger = 7,
+    Select = 8,
+    Start = 9,
+    DpadUp = 12,
+    DpadDown = 13,
+    DpadLeft = 14,
+    DpadRight = 15,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 14] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::DpadUp,
+        GamepadButton::DpadDown,
+        GamepadButton::DpadLeft,
+        GamepadButton::DpadRight,
+    ];
+
+    fn bit(&self) -> u32 {
+        1 << (*self as u32)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct GamepadState {
+    pub connected: bool,
+    // stick gauche, déjà corrigé de la zone morte
+    pub stick: Vec2,
+    buttons: u32,
+    previous_buttons: u32,
+}
+
+impl GamepadState {
+    // nouvel état des boutons (l'ancien sert à détecter les appuis)
+    pub fn set_buttons(&mut self, buttons: &[GamepadButton]) {
+        self.previous_buttons = self.buttons;
+        self.buttons = buttons.iter().fold(0, |bits, button| bits | button.bit());
+    }
+
+    // position brute du stick, on applique la zone morte
+    pub fn set_stick(&mut self, raw: Vec2) {
+        let length = raw.length();
+        self.stick = if length < DEAD_ZONE {
+            Vec2::ZERO
+        } else {
+            raw / length * ((length - DEAD_ZONE) / (1.0 - DEAD_ZONE)).min(1.0)
+        };
+    }
+
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.buttons & button.bit() != 0
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.is_down(button) && self.previous_buttons & button.bit() == 0
+    }
+
+    // nouvel état lu sur la manette, ou manette débranchée
+    fn update(&mut self, read: Option<(Vec2, Vec<GamepadButton>)>) {
+        match read {
+            Some((stick, buttons)) => {
+                self.connected = true;
+                self.set_stick(stick);
+                self.set_buttons(&buttons);
+            }
+            None => *self = GamepadState::default(),
+        }
+    }
+}
+
+// lecture de la vraie manette : gamepad.js dans le navigateur, gilrs sur ordinateur
+pub struct GamepadReader {
+    #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+impl GamepadReader {
+    pub fn new() -> GamepadReader {
+        GamepadReader {
+            // sans accès aux manettes (pilote absent, droits), le jeu se joue sans
+            #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+            gilrs: gilrs::Gilrs::new()
+                .inspect_err(|e| warn!("gamepads unavailable: {}", e))
+                .ok(),
+        }
+    }
+
+    // lecture de la manette, à faire une fois par frame
+    pub fn poll(&mut self, state: &mut GamepadState) {
+        state.update(self.read());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read(&mut self) -> Option<(Vec2, Vec<GamepadButton>)> {
+        unsafe extern "C" {
+            fn gamepad_connected() -> i32;
+            fn gamepad_axis(axis: i32) -> f32;
+            fn gamepad_button(button: i32) -> i32;
+        }
+        // SAFETY: fonctions fournies par gamepad.js, sans effet de bord
+        unsafe {
+            if gamepad_connected() == 0 {
+                return None;
+            }
+            let stick = vec2(gamepad_axis(0), gamepad_axis(1));
+            let buttons = GamepadButton::ALL
+                .into_iter()
+                .filter(|button| gamepad_button(*button as i32) != 0)
+                .collect();
+            Some((stick, buttons))
+        }
+    }
+
+    // première manette branchée ; gilrs compte l'axe vertical vers le haut
+    #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+    fn read(&mut self) -> Option<(Vec2, Vec<GamepadButton>)> {
+        use gilrs::{Axis, Button};
+        let gilrs = self.gilrs.as_mut()?;
+        // les événements mettent à jour l'état des manettes gardé par gilrs
+        while gilrs.next_event().is_some() {}
+        let (_, gamepad) = gilrs.gamepads().next()?;
+        let stick = vec2(
+            gamepad.value(Axis::LeftStickX),
+            -gamepad.value(Axis::LeftStickY),
+        );
+        let button = |button: GamepadButton| match button {
+            GamepadButton::South => Button::South,
+            GamepadButton::East => Button::East,
+            GamepadButton::West => Button::West,
+            GamepadButton::North => Button::North,
+            GamepadButton::LeftShoulder => Button::LeftTrigger,
+            GamepadButton::RightShoulder => Button::RightTrigger,
+            GamepadButton::LeftTrigger => Button::LeftTrigger2,
+            GamepadButton::RightTrigger => Button::RightTrigger2,
+            GamepadButton::Select => Button::Select,
+            GamepadButton::Start => Button::Start,
+            GamepadButton::DpadUp => Button::DPadUp,
+            GamepadButton::DpadDown => Button::DPadDown,
+            GamepadButton::DpadLeft => Button::DPadLeft,
+            GamepadButton::DpadRight => Button::DPadRight,
+        };
+        let buttons = GamepadButton::ALL
+            .into_iter()
+            .filter(|b| gamepad.is_pressed(button(*b)))
+            .collect();
+        Some((stick, buttons))
+    }
+
+    // compilé sans la fonctionnalité "gamepad" : pas de manette sur ordinateur
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "gamepad")))]
+    fn read(&mut self) -> Option<(Vec2, Vec<GamepadButton>)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stick_inside_dead_zone_is_at_rest() {
+        let mut pad = GamepadState::default();
+        pad.set_stick(vec2(0.1, -0.15));
+        assert_eq!(pad.stick, Vec2::ZERO);
+    }
+
+    #[test]
+    fn stick_is_rescaled_outside_dead_zone() {
+        let mut pad = GamepadState::default();
+        pad.set_stick(vec2(0.6, 0.0));
+        assert!((pad.stick.x - 0.5).abs() < 1e-6);
+        assert_eq!(pad.stick.y, 0.0);
+        // au bord de la zone morte le stick repart de 0, au bout il vaut 1
+        pad.set_stick(vec2(0.0, -DEAD_ZONE));
+        assert!(pad.stick.length() < 1e-6);
+        pad.set_stick(vec2(0.0, -1.5));
+        assert_eq!(pad.stick, vec2(0.0, -1.0));
+    }
+
+    #[test]
+    fn button_is_pressed_only_on_the_first_frame() {
+        let mut pad = GamepadState::default();
+        pad.set_buttons(&[GamepadButton::South]);
+        assert!(pad.is_pressed(GamepadButton::South));
+        assert!(!pad.is_pressed(GamepadButton::East));
+        pad.set_buttons(&[GamepadButton::South, GamepadButton::East]);
+        assert!(pad.is_down(GamepadButton::South));
+        assert!(!pad.is_pressed(GamepadButton::South));
+        assert!(pad.is_pressed(GamepadButton::East));
+        pad.set_buttons(&[]);
+        assert!(!pad.is_down(GamepadButton::South));
+        pad.set_buttons(&[GamepadButton::South]);
+        assert!(pad.is_pressed(GamepadButton::South));
+    }
+
+    #[test]
+    fn unplugged_gamepad_is_reset() {
+        let mut pad = GamepadState::default();
+        pad.update(Some((vec2(1.0, 0.0), vec![GamepadButton::Start])));
+        assert!(pad.connected);
+        assert!(pad.is_pressed(GamepadButton::Start));
+        pad.update(None);
+        assert!(!pad.connected);
+        assert_eq!(pad.stick, Vec2::ZERO);
+        assert!(!pad.is_down(GamepadButton::Start));
+    }
+}
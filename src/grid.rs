@@ -0,0 +1,195 @@
+/* Structure SpatialGrid (présélection des collisions par une grille uniforme) */
+
+use crate::Shape;
+use macroquad::prelude::*;
+
+pub struct SpatialGrid {
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    // index des formes présentes dans chaque case
+    cells: Vec<Vec<usize>>,
+    // numéro de la dernière requête ayant trouvé chaque forme (évite les doublons)
+    marks: Vec<u32>,
+    query: u32,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> SpatialGrid {
+        SpatialGrid {
+            cell_size,
+            cols: 0,
+            rows: 0,
+            cells: vec![],
+            marks: vec![],
+            query: 0,
+        }
+    }
+
+    // cases couvertes par un rectangle ; ce qui dépasse de l'écran va dans les cases du bord
+    fn cell_range(&self, rect: &Rect) -> (usize, usize, usize, usize) {
+        let cell = |value: f32, count: usize| {
+            ((value / self.cell_size).floor().max(0.0) as usize).min(count - 1)
+        };
+        (
+            cell(rect.x, self.cols),
+            cell(rect.x + rect.w, self.cols),
+            cell(rect.y, self.rows),
+            cell(rect.y + rect.h, self.rows),
+        )
+    }
+
+    // répartition des formes dans la grille, refaite à chaque pas
+    pub fn rebuild(&mut self, shapes: &[Shape], width: f32, height: f32) {
+        self.cols = ((width / self.cell_size).ceil() as usize).max(1);
+        self.rows = ((height / self.cell_size).ceil() as usize).max(1);
+        // on garde la mémoire des cases d'un pas à l'autre
+        self.cells.resize_with(self.cols * self.rows, Vec::new);
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        self.marks.clear();
+        self.marks.resize(shapes.len(), 0);
+        self.query = 0;
+        for (index, shape) in shapes.iter().enumerate() {
+            let (x0, x1, y0, y1) = self.cell_range(&shape.bounds());
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.cells[y * self.cols + x].push(index);
+                }
+            }
+        }
+    }
+
+    // formes dont la case croise le rectangle, sans doublon, dans l'ordre de la liste
+    pub fn query(&mut self, rect: &Rect, found: &mut Vec<usize>) {
+        found.clear();
+        self.query += 1;
+        let (x0, x1, y0, y1) = self.cell_range(rect);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                for &index in &self.cells[y * self.cols + x] {
+                    if self.marks[index] != self.query {
+                        self.marks[index] = self.query;
+                        found.push(index);
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collider::Collider;
+
+    const WIDTH: f32 = 300.0;
+    const HEIGHT: f32 = 200.0;
+
+    fn shape(x: f32, y: f32, collider: Collider) -> Shape {
+        Shape {
+            size: 0.0,
+            speed: 0.0,
+            x,
+            y,
+            collided: false,
+            life: 1,
+            prev_x: x,
+            prev_y: y,
+            velocity: Vec2::ZERO,
+            kind: 0,
+            collider,
+        }
+    }
+
+    // formes de tailles variées sur un pas qui ne tombe pas sur les cases, y compris
+    // à cheval sur les bords des cases et hors de la grille
+    fn shapes(step: f32, offset: f32) -> Vec<Shape> {
+        let mut shapes = vec![];
+        let mut i = 0;
+        let mut y = -60.0 + offset;
+        while y < HEIGHT + 60.0 {
+            let mut x = -60.0 + offset;
+            while x < WIDTH + 60.0 {
+                let collider = match i % 3 {
+                    0 => Collider::Circle {
+                        center: Vec2::ZERO,
+                        radius: 3.0 + (i % 7) as f32 * 4.0,
+                    },
+                    1 => Collider::Aabb {
+                        center: vec2(2.0, -1.0),
+                        half: vec2(4.0 + (i % 5) as f32 * 6.0, 2.0 + (i % 4) as f32 * 5.0),
+                    },
+                    _ => Collider::Obb {
+                        center: Vec2::ZERO,
+                        half: vec2(12.0, 3.0),
+                        angle: i as f32 * 0.7,
+                    },
+                };
+                shapes.push(shape(x, y, collider));
+                x += step;
+                i += 1;
+            }
+            y += step;
+        }
+        // sur une limite de case, et loin en dehors de l'écran
+        for (x, y) in [
+            (64.0, 64.0),
+            (-500.0, 90.0),
+            (WIDTH + 500.0, HEIGHT + 500.0),
+        ] {
+            let collider = Collider::Circle {
+                center: Vec2::ZERO,
+                radius: 8.0,
+            };
+            shapes.push(shape(x, y, collider));
+        }
+        shapes
+    }
+
+    fn touches(a: &Rect, b: &Rect) -> bool {
+        a.x <= b.x + b.w && b.x <= a.x + a.w && a.y <= b.y + b.h && b.y <= a.y + a.h
+    }
+
+    #[test]
+    fn grid_finds_the_same_pairs_as_brute_force() {
+        let queries = shapes(23.0, 0.0);
+        let targets = shapes(17.0, 5.0);
+        let mut grid = SpatialGrid::new(64.0);
+        grid.rebuild(&targets, WIDTH, HEIGHT);
+        let mut found = vec![];
+        let (mut grid_pairs, mut brute_pairs) = (vec![], vec![]);
+        for (i, query) in queries.iter().enumerate() {
+            grid.query(&query.bounds(), &mut found);
+            // chaque forme dont le rectangle englobant touche la requête est proposée
+            for (j, target) in targets.iter().enumerate() {
+                if touches(&query.bounds(), &target.bounds()) {
+                    assert!(found.contains(&j), "pair {} {} missed by the grid", i, j);
+                }
+                if query.collides_with(target) {
+                    brute_pairs.push((i, j));
+                }
+            }
+            for &j in &found {
+                if query.collides_with(&targets[j]) {
+                    grid_pairs.push((i, j));
+                }
+            }
+        }
+        assert!(!brute_pairs.is_empty());
+        assert_eq!(grid_pairs, brute_pairs);
+    }
+
+    #[test]
+    fn query_returns_each_shape_once_in_order() {
+        let targets = shapes(17.0, 5.0);
+        let mut grid = SpatialGrid::new(64.0);
+        grid.rebuild(&targets, WIDTH, HEIGHT);
+        let mut found = vec![];
+        // plus grand que l'écran : tout le monde, une seule fois
+        grid.query(&Rect::new(-1000.0, -1000.0, 3000.0, 3000.0), &mut found);
+        assert_eq!(found, (0..targets.len()).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,91 @@
+/* Structure HotReload (surveillance du dossier assets pour le rechargement à chaud, version native) */
+
+use crate::archetypes::*;
+use crate::data::*;
+use crate::level::Level;
+use crate::music::*;
+use crate::waves::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// intervalle entre deux relevés des dates de modification, en secondes
+const POLL_INTERVAL: f32 = 0.5;
+
+pub struct HotReload {
+    folder: PathBuf,
+    // date de modification de chaque fichier au dernier relevé
+    mtimes: HashMap<String, SystemTime>,
+    elapsed: f32,
+}
+
+impl HotReload {
+    pub fn new(folder: &str) -> HotReload {
+        let folder = PathBuf::from(folder);
+        let mtimes = HotReload::scan(&folder);
+        HotReload {
+            folder,
+            mtimes,
+            elapsed: 0.0,
+        }
+    }
+
+    // dates de modification des fichiers du dossier (sans les sous-dossiers)
+    fn scan(folder: &PathBuf) -> HashMap<String, SystemTime> {
+        let Ok(entries) = std::fs::read_dir(folder) else {
+            return HashMap::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                if !metadata.is_file() {
+                    return None;
+                }
+                let name = entry.file_name().into_string().ok()?;
+                Some((name, metadata.modified().ok()?))
+            })
+            .collect()
+    }
+
+    // fichiers créés ou modifiés depuis le relevé précédent
+    pub fn poll(&mut self, delta_time: f32) -> Vec<String> {
+        self.elapsed += delta_time;
+        if self.elapsed < POLL_INTERVAL {
+            return vec![];
+        }
+        self.elapsed = 0.0;
+        let mtimes = HotReload::scan(&self.folder);
+        let mut changed: Vec<String> = mtimes
+            .iter()
+            .filter(|(file, mtime)| self.mtimes.get(*file) != Some(mtime))
+            .map(|(file, _)| file.clone())
+            .collect();
+        changed.sort();
+        self.mtimes = mtimes;
+        changed
+    }
+}
+
+// relecture stricte des fichiers de données : pas de repli sur les données par défaut,
+// l'erreur est affichée et la partie continue avec les données actuelles
+pub async fn reload_data(level_file: &str) -> Result<(Level, Archetypes, WaveList), DataError> {
+    let level = Level::read(level_file).await?;
+    let archetypes = Archetypes::parse(
+        DEFAULT_ARCHETYPES_FILE,
+        &load_data_file(DEFAULT_ARCHETYPES_FILE).await?,
+    )?;
+    let waves = WaveList::parse(
+        DEFAULT_WAVES_FILE,
+        &load_data_file(DEFAULT_WAVES_FILE).await?,
+        &archetypes,
+    )?;
+    Ok((level, archetypes, waves))
+}
+
+pub async fn reload_music() -> Result<MusicList, DataError> {
+    MusicList::parse(
+        DEFAULT_MUSIC_FILE,
+        &load_data_file(DEFAULT_MUSIC_FILE).await?,
+    )
+}
@@ -0,0 +1,119 @@
+/* Structure Hud (messages affichés par-dessus la partie) */
+
+use crate::events::*;
+use crate::text_display::draw_centered;
+use macroquad::prelude::*;
+
+// durée d'affichage d'un message, en secondes
+const MESSAGE_DURATION: f32 = 2.0;
+// durée de l'éclair rouge quand le vaisseau est touché
+const FLASH_DURATION: f32 = 0.25;
+// durée des secousses de l'écran et leur amplitude en pixels, à l'intensité maximale
+const SHAKE_DURATION: f32 = 0.35;
+const SHIP_HIT_SHAKE: f32 = 10.0;
+const BUILDING_DESTROYED_SHAKE: f32 = 6.0;
+
+pub struct Hud {
+    // message et temps restant
+    messages: Vec<(String, f32)>,
+    flash: f32,
+    // secousse en cours : amplitude et temps restant
+    shake: (f32, f32),
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            messages: vec![],
+            flash: 0.0,
+            shake: (0.0, 0.0),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.flash = 0.0;
+        self.shake = (0.0, 0.0);
+    }
+
+    fn message(&mut self, text: String) {
+        self.messages.push((text, MESSAGE_DURATION));
+    }
+
+    // une secousse plus forte remplace celle en cours
+    fn shake(&mut self, amplitude: f32) {
+        if amplitude >= self.shake.0 * self.shake.1 / SHAKE_DURATION {
+            self.shake = (amplitude, SHAKE_DURATION);
+        }
+    }
+
+    // décalage de l'affichage de la partie, qui décroît pendant la secousse ;
+    // intensity vient des réglages (0 : pas de secousse)
+    pub fn shake_offset(&self, intensity: f32) -> Vec2 {
+        let (amplitude, time) = self.shake;
+        if time <= 0.0 || intensity <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let strength = amplitude * intensity * time / SHAKE_DURATION;
+        vec2(
+            rand::gen_range(-strength, strength),
+            rand::gen_range(-strength, strength),
+        )
+    }
+
+    // les messages s'effacent au bout de MESSAGE_DURATION secondes
+    pub fn update(&mut self, delta_time: f32) {
+        self.flash = (self.flash - delta_time).max(0.0);
+        self.shake.1 = (self.shake.1 - delta_time).max(0.0);
+        for (_, time) in self.messages.iter_mut() {
+            *time -= delta_time;
+        }
+        self.messages.retain(|(_, time)| *time > 0.0);
+    }
+
+    pub fn display(&self, font: Option<&Font>) {
+        if self.flash > 0.0 {
+            let alpha = 0.4 * self.flash / FLASH_DURATION;
+            draw_rectangle(
+                0.0,
+                0.0,
+                screen_width(),
+                screen_height(),
+                Color::new(1.0, 0.0, 0.0, alpha),
+            );
+        }
+        let mut text_params = TextParams {
+            font_size: 25,
+            font,
+            color: ORANGE,
+            font_scale: 1.0,
+            ..Default::default()
+        };
+        for (i, (text, time)) in self.messages.iter().enumerate() {
+            // le message pâlit pendant sa dernière seconde
+            text_params.color.a = time.min(1.0);
+            draw_centered(text, screen_height() * 0.3 + i as f32 * 30.0, &text_params);
+        }
+    }
+}
+
+// messages de la partie : bâtiment détruit, munitions, dernière vie ; secousses de l'écran
+impl Subscriber for Hud {
+    fn notify(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::BuildingDestroyed { name } => {
+                self.message(format!("{} destroyed!", name));
+                self.shake(BUILDING_DESTROYED_SHAKE);
+            }
+            GameEvent::AmmoEmpty => self.message("Out of ammo!".to_string()),
+            GameEvent::ShipHit { lives, .. } => {
+                self.flash = FLASH_DURATION;
+                self.shake(SHIP_HIT_SHAKE);
+                if *lives == 0 {
+                    self.message("Last ship!".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,493 @@
+/* Structure InputMap (association des touches aux actions) */
+
+use crate::gamepad::*;
+use crate::settings::Settings;
+use crate::touch::*;
+use crate::world::InputState;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+const STORAGE_KEY: &str = "bindings";
+// boutons de souris qu'on peut associer à une action
+pub const BINDABLE_MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+// source des entrées : le vrai clavier, la manette et l'écran tactile, ou un état simulé
+pub trait InputBackend {
+    fn key_down(&self, key: KeyCode) -> bool;
+    fn key_pressed(&self, key: KeyCode) -> bool;
+    fn mouse_down(&self, button: MouseButton) -> bool;
+    fn mouse_pressed(&self, button: MouseButton) -> bool;
+    fn mouse_position(&self) -> Vec2;
+    fn gamepad(&self) -> &GamepadState;
+    fn touch(&self) -> &TouchState;
+}
+
+// entrées réelles lues via macroquad (et la manette via GamepadReader)
+pub struct MacroquadBackend {
+    gamepad: GamepadState,
+    reader: GamepadReader,
+    pub touch: TouchControls,
+}
+
+impl MacroquadBackend {
+    pub fn new() -> MacroquadBackend {
+        MacroquadBackend {
+            gamepad: GamepadState::default(),
+            reader: GamepadReader::new(),
+            touch: TouchControls::new(),
+        }
+    }
+
+    // à appeler une fois par frame ; le bouton AUTO de l'écran tactile change les réglages
+    pub fn update(&mut self, settings: &mut Settings) {
+        self.reader.poll(&mut self.gamepad);
+        self.touch.update(settings);
+    }
+}
+
+impl InputBackend for MacroquadBackend {
+    fn key_down(&self, key: KeyCode) -> bool {
+        is_key_down(key)
+    }
+
+    fn key_pressed(&self, key: KeyCode) -> bool {
+        is_key_pressed(key)
+    }
+
+    fn mouse_down(&self, button: MouseButton) -> bool {
+        is_mouse_button_down(button)
+    }
+
+    fn mouse_pressed(&self, button: MouseButton) -> bool {
+        is_mouse_button_pressed(button)
+    }
+
+    fn mouse_position(&self) -> Vec2 {
+        mouse_position().into()
+    }
+
+    fn gamepad(&self) -> &GamepadState {
+        &self.gamepad
+    }
+
+    fn touch(&self) -> &TouchState {
+        &self.touch.state
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Pause,
+    Confirm,
+    Back,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::Restart,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::Fire => "Fire",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Restart => "Restart replay",
+        }
+    }
+
+    fn from_debug_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| format!("{:?}", action) == name)
+    }
+
+    // Escape sert à Pause et à Back : pendant la partie seule Pause est lue,
+    // Back ne l'est que dans les menus et la relecture (qui ne lit pas Pause)
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Action::MoveLeft => vec![KeyCode::Left, KeyCode::A, KeyCode::Q],
+            Action::MoveRight => vec![KeyCode::Right, KeyCode::D],
+            Action::MoveUp => vec![KeyCode::Up, KeyCode::W, KeyCode::Z],
+            Action::MoveDown => vec![KeyCode::Down, KeyCode::S],
+            Action::Fire => vec![KeyCode::Space],
+            Action::Pause => vec![KeyCode::Escape, KeyCode::P],
+            Action::Confirm => vec![KeyCode::Enter, KeyCode::KpEnter],
+            Action::Back => vec![KeyCode::Escape, KeyCode::Backspace],
+            Action::Restart => vec![KeyCode::Home],
+        }
+    }
+
+    fn default_buttons(&self) -> Vec<GamepadButton> {
+        match self {
+            Action::MoveLeft => vec![GamepadButton::DpadLeft],
+            Action::MoveRight => vec![GamepadButton::DpadRight],
+            Action::MoveUp => vec![GamepadButton::DpadUp],
+            Action::MoveDown => vec![GamepadButton::DpadDown],
+            Action::Fire => vec![GamepadButton::West, GamepadButton::RightShoulder],
+            Action::Pause => vec![GamepadButton::Start],
+            Action::Confirm => vec![GamepadButton::South],
+            Action::Back => vec![GamepadButton::East],
+            Action::Restart => vec![GamepadButton::Select],
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        let keys = self.default_keys().into_iter().map(Binding::Key);
+        let buttons = self.default_buttons().into_iter().map(Binding::Gamepad);
+        let mut bindings: Vec<Binding> = keys.chain(buttons).collect();
+        if *self == Action::Fire {
+            bindings.push(Binding::Mouse(MouseButton::Left));
+        }
+        bindings
+    }
+}
+
+// une entrée physique pouvant déclencher une action
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButton),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Gamepad(button) => format!("Pad{:?}", button),
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+        }
+    }
+
+    fn parse(name: &str) -> Option<Binding> {
+        if let Some(button) = name.strip_prefix("Pad") {
+            return GamepadButton::ALL
+                .into_iter()
+                .find(|b| format!("{:?}", b) == button)
+                .map(Binding::Gamepad);
+        }
+        if let Some(button) = name.strip_prefix("Mouse") {
+            return BINDABLE_MOUSE_BUTTONS
+                .into_iter()
+                .find(|b| format!("{:?}", b) == button)
+                .map(Binding::Mouse);
+        }
+        BINDABLE_KEYS
+            .into_iter()
+            .find(|key| format!("{:?}", key) == name)
+            .map(Binding::Key)
+    }
+
+    // sur écran tactile, chaque doigt posé simule un clic gauche (voir main) : les boutons
+    // de souris sont ignorés, sinon toucher le stick ou la pause ferait aussi tirer
+    fn is_down(&self, backend: &dyn InputBackend) -> bool {
+        match self {
+            Binding::Key(key) => backend.key_down(*key),
+            Binding::Gamepad(button) => backend.gamepad().is_down(*button),
+            Binding::Mouse(button) => !backend.touch().active && backend.mouse_down(*button),
+        }
+    }
+
+    fn is_pressed(&self, backend: &dyn InputBackend) -> bool {
+        match self {
+            Binding::Key(key) => backend.key_pressed(*key),
+            Binding::Gamepad(button) => backend.gamepad().is_pressed(*button),
+            Binding::Mouse(button) => !backend.touch().active && backend.mouse_pressed(*button),
+        }
+    }
+}
+
+// touches qu'on peut associer à une action (et relire depuis le stockage)
+pub const BINDABLE_KEYS: [KeyCode; 60] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::KpEnter,
+    KeyCode::Escape,
+    KeyCode::Backspace,
+    KeyCode::Tab,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::Kp0,
+    KeyCode::Kp2,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp8,
+    KeyCode::Comma,
+    KeyCode::Period,
+];
+
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn new() -> InputMap {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| (action, action.default_bindings()))
+            .collect();
+        InputMap { bindings }
+    }
+
+    // lecture depuis le stockage : "MoveLeft:Left,A,Q;MoveRight:Right,D;..."
+    pub fn load() -> InputMap {
+        let mut map = InputMap::new();
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        if let Some(saved) = storage.get(STORAGE_KEY) {
+            for entry in saved.split(';') {
+                let Some((action, keys)) = entry.split_once(':') else {
+                    continue;
+                };
+                let Some(action) = Action::from_debug_name(action) else {
+                    continue;
+                };
+                let keys: Vec<Binding> = keys.split(',').filter_map(Binding::parse).collect();
+                map.bindings.insert(action, keys);
+            }
+        }
+        map
+    }
+
+    pub fn save(&self) {
+        let saved = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys: Vec<String> = self.get(action).iter().map(Binding::name).collect();
+                format!("{:?}:{}", action, keys.join(","))
+            })
+            .collect::<Vec<String>>()
+            .join(";");
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        storage.set(STORAGE_KEY, &saved);
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.insert(action, vec![]);
+    }
+
+    pub fn is_down(&self, action: Action, backend: &dyn InputBackend) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.is_down(backend))
+    }
+
+    pub fn is_pressed(&self, action: Action, backend: &dyn InputBackend) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.is_pressed(backend))
+    }
+
+    // nom de la première touche d'une action, pour les messages à l'écran
+    pub fn key_name(&self, action: Action) -> String {
+        self.get(action)
+            .first()
+            .map(Binding::name)
+            .unwrap_or_else(|| "?".to_string())
+    }
+
+    // validation d'un écran : action Confirm ou simple toucher de l'écran
+    pub fn is_confirmed(&self, backend: &dyn InputBackend) -> bool {
+        self.is_pressed(Action::Confirm, backend) || backend.touch().tapped
+    }
+
+    // état des commandes du vaisseau pour une frame
+    // le stick donne une vitesse proportionnelle, les touches la vitesse maximale ;
+    // en visée à la souris, le point visé est la position du curseur
+    pub fn read(&self, backend: &dyn InputBackend, mouse_aim: bool) -> InputState {
+        let axis = |negative: Action, positive: Action| {
+            let mut value = 0.0;
+            if self.is_down(negative, backend) {
+                value -= 1.0;
+            }
+            if self.is_down(positive, backend) {
+                value += 1.0;
+            }
+            value
+        };
+        let keys = vec2(
+            axis(Action::MoveLeft, Action::MoveRight),
+            axis(Action::MoveUp, Action::MoveDown),
+        );
+        let movement = if keys != Vec2::ZERO {
+            keys
+        } else if backend.gamepad().stick != Vec2::ZERO {
+            backend.gamepad().stick
+        } else {
+            backend.touch().stick
+        };
+        InputState {
+            movement: vec2(quantize_axis(movement.x), quantize_axis(movement.y)),
+            shoot: self.is_pressed(Action::Fire, backend) || backend.touch().fire,
+            pause: self.is_pressed(Action::Pause, backend) || backend.touch().pause,
+            aim: mouse_aim.then(|| backend.mouse_position().round()),
+        }
+    }
+}
+
+// les axes sont arrondis au 1/127e : c'est la précision stockée dans les replays
+pub fn quantize_axis(value: f32) -> f32 {
+    (value.clamp(-1.0, 1.0) * 127.0).round() / 127.0
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // entrées simulées : la manette est pilotée par set_stick et set_buttons
+    #[derive(Default)]
+    pub struct FakeBackend {
+        pub keys: Vec<KeyCode>,
+        pub mouse: Vec<MouseButton>,
+        pub gamepad: GamepadState,
+        pub touch: TouchState,
+    }
+
+    impl FakeBackend {
+        // une frame de plus avec ces boutons enfoncés
+        pub fn hold(&mut self, buttons: &[GamepadButton]) {
+            self.gamepad.set_buttons(buttons);
+        }
+    }
+
+    impl InputBackend for FakeBackend {
+        fn key_down(&self, key: KeyCode) -> bool {
+            self.keys.contains(&key)
+        }
+
+        fn key_pressed(&self, key: KeyCode) -> bool {
+            self.keys.contains(&key)
+        }
+
+        fn mouse_down(&self, button: MouseButton) -> bool {
+            self.mouse.contains(&button)
+        }
+
+        fn mouse_pressed(&self, button: MouseButton) -> bool {
+            self.mouse.contains(&button)
+        }
+
+        fn mouse_position(&self) -> Vec2 {
+            Vec2::ZERO
+        }
+
+        fn gamepad(&self) -> &GamepadState {
+            &self.gamepad
+        }
+
+        fn touch(&self) -> &TouchState {
+            &self.touch
+        }
+    }
+
+    #[test]
+    fn stick_moves_the_ship_unless_keys_are_down() {
+        let map = InputMap::new();
+        let mut backend = FakeBackend::default();
+        backend.gamepad.set_stick(vec2(0.1, 0.0));
+        assert_eq!(map.read(&backend, false).movement, Vec2::ZERO);
+        backend.gamepad.set_stick(vec2(-1.0, 0.0));
+        assert_eq!(map.read(&backend, false).movement, vec2(-1.0, 0.0));
+        backend.keys.push(KeyCode::Right);
+        assert_eq!(map.read(&backend, false).movement, vec2(1.0, 0.0));
+    }
+
+    #[test]
+    fn held_fire_button_shoots_once() {
+        let map = InputMap::new();
+        let mut backend = FakeBackend::default();
+        backend.hold(&[GamepadButton::West]);
+        assert!(map.read(&backend, false).shoot);
+        backend.hold(&[GamepadButton::West]);
+        assert!(!map.read(&backend, false).shoot);
+        assert!(map.is_down(Action::Fire, &backend));
+        backend.hold(&[GamepadButton::Start]);
+        assert!(map.read(&backend, false).pause);
+    }
+
+    #[test]
+    fn touches_do_not_count_as_mouse_clicks() {
+        let map = InputMap::new();
+        let mut backend = FakeBackend::default();
+        backend.mouse.push(MouseButton::Left);
+        assert!(map.read(&backend, false).shoot);
+        backend.touch.active = true;
+        assert!(!map.read(&backend, false).shoot);
+    }
+}
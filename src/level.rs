@@ -0,0 +1,170 @@
+/* Structure Level (disposition de la ville lue dans un fichier) */
+
+use crate::collider::ColliderDef;
+use crate::data::*;
+use nanoserde::DeRon;
+
+pub const DEFAULT_LEVEL_FILE: &str = "city.ron";
+// la ville livrée avec le jeu
+const DEFAULT_LEVEL: &str = include_str!("../assets/city.ron");
+
+#[derive(DeRon, Clone)]
+pub struct BuildingDef {
+    pub name: String,
+    pub texture: String,
+    pub width: u32,
+    pub height: u32,
+    pub x: f32,
+    pub life: i32,
+    // forme de collision dans l'image ; par défaut toute l'image
+    #[nserde(default)]
+    pub collider: ColliderDef,
+}
+
+#[derive(DeRon, Clone)]
+pub struct Level {
+    pub name: String,
+    pub buildings: Vec<BuildingDef>,
+}
+
+impl Level {
+    // lecture et vérification d'un fichier de ville
+    pub fn parse(file: &str, text: &str) -> Result<Level, DataError> {
+        let level =
+            Level::deserialize_ron(text).map_err(|e| DataError::Parse(file.to_string(), e))?;
+        if level.buildings.is_empty() {
+            return Err(DataError::Empty(file.to_string()));
+        }
+        for (i, building) in level.buildings.iter().enumerate() {
+            let invalid = |message: &str| DataError::Invalid {
+                file: file.to_string(),
+                entry: i,
+                name: building.name.clone(),
+                message: message.to_string(),
+            };
+            if building.name.is_empty() {
+                return Err(invalid("name is empty"));
+            }
+            // les dégâts sont retrouvés par nom au rechargement
+            if level.buildings[..i].iter().any(|b| b.name == building.name) {
+                return Err(invalid("name is already used by another building"));
+            }
+            if !building.texture.ends_with(".png") {
+                return Err(invalid("texture must be a .png file"));
+            }
+            if building.width == 0 || building.height == 0 {
+                return Err(invalid("width and height must be greater than 0"));
+            }
+            if building.life <= 0 {
+                return Err(invalid("life must be greater than 0"));
+            }
+            if !building.x.is_finite() || building.x < 0.0 {
+                return Err(invalid("x must be a positive number"));
+            }
+            building.collider.check().map_err(invalid)?;
+        }
+        Ok(level)
+    }
+
+    // la ville livrée avec le jeu
+    pub fn default_city() -> Level {
+        Level::parse(DEFAULT_LEVEL_FILE, DEFAULT_LEVEL).expect("invalid built-in city")
+    }
+
+    // lecture sans repli sur la ville par défaut (relectures, rechargement à chaud)
+    pub async fn read(file: &str) -> Result<Level, DataError> {
+        Level::parse(file, &load_data_file(file).await?)
+    }
+
+    // chargement depuis le dossier assets ; en cas d'erreur on garde la ville par défaut
+    pub async fn load(file: &str) -> (Level, Option<DataError>) {
+        load_or_default(file, |text| Level::parse(file, text), Level::default_city).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn building(name: &str, width: u32) -> String {
+        format!(
+            "(name: \"{}\", texture: \"temple.png\", width: {}, height: 32, x: 10.0, life: 3)",
+            name, width
+        )
+    }
+
+    fn city(buildings: &[String]) -> String {
+        format!("(name: \"test\", buildings: [{}])", buildings.join(", "))
+    }
+
+    fn error(text: &str) -> String {
+        Level::parse("test.ron", text).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn valid_city_is_read() {
+        let level = Level::parse("test.ron", &city(&[building("a", 32), building("b", 16)]));
+        let names: Vec<String> = level
+            .unwrap()
+            .buildings
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn malformed_file_gives_the_position_of_the_error() {
+        let text = error("(name: \"test\",\n buildings: [(name: 3)])");
+        assert!(
+            text.starts_with("test.ron: syntax error line 2 column "),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn bad_entries_are_named_in_the_error() {
+        assert_eq!(
+            error(&city(&[building("a", 32), building("b", 0)])),
+            "test.ron: entry #2 \"b\": width and height must be greater than 0"
+        );
+        assert_eq!(
+            error(&city(&[
+                building("a", 32),
+                building("b", 32),
+                building("a", 16)
+            ])),
+            "test.ron: entry #3 \"a\": name is already used by another building"
+        );
+        assert_eq!(error(&city(&[])), "test.ron: the file has no entry");
+    }
+
+    #[test]
+    fn unreadable_or_invalid_file_falls_back_to_the_default_city() {
+        let default = Level::default_city();
+        let missing = Err(DataError::Io(
+            "city.ron".to_string(),
+            "not found".to_string(),
+        ));
+        let (level, error) = parse_or_default(
+            missing,
+            |t| Level::parse("city.ron", t),
+            Level::default_city,
+        );
+        assert_eq!(level.buildings.len(), default.buildings.len());
+        assert_eq!(error.unwrap().to_string(), "city.ron: not found");
+        let invalid = Ok(city(&[building("a", 0)]));
+        let (level, error) = parse_or_default(
+            invalid,
+            |t| Level::parse("city.ron", t),
+            Level::default_city,
+        );
+        assert_eq!(level.name, default.name);
+        assert!(error.unwrap().to_string().contains("width and height"));
+        let valid = Ok(city(&[building("a", 32)]));
+        let (level, error) =
+            parse_or_default(valid, |t| Level::parse("city.ron", t), Level::default_city);
+        assert_eq!((level.buildings.len(), error.is_none()), (1, true));
+    }
+}
@@ -1,27 +1,70 @@
+mod achievements;
+mod archetypes;
+mod assets;
+mod atlas;
 mod buildings;
 mod bullets;
+mod collider;
+mod controls;
+mod data;
 mod enemies;
+mod events;
 mod explosions;
+mod gamepad;
+mod grid;
+mod hot_reload;
+mod hud;
+mod input;
+mod level;
+mod masks;
 mod menu;
+mod mixer;
 mod music;
+mod options;
+mod renderer;
+mod replay;
 mod score;
+mod settings;
 mod shader;
 mod ship;
+mod sound_variants;
+mod sounds;
 mod text_display;
+mod timestep;
+mod touch;
+mod waves;
+mod world;
 
-use buildings::*;
-use bullets::*;
-use enemies::*;
+use achievements::Achievements;
+use archetypes::*;
+use assets::*;
+use collider::Collider;
+use controls::*;
+use events::*;
 use explosions::*;
+use hot_reload::*;
+use hud::Hud;
+use input::*;
+use level::*;
+use masks::MaskLibrary;
 use menu::*;
+use mixer::Mixer;
 use music::*;
-use score::*;
+use options::*;
+use renderer::*;
+use replay::*;
+use score::Score;
+use settings::*;
 use shader::*;
-use ship::*;
+use sounds::SoundEffects;
 use text_display::*;
+use timestep::*;
+use waves::*;
+use world::*;
 
 use crate::miniquad::window::set_window_position;
 use macroquad::prelude::*;
+use macroquad::ui::root_ui;
 
 //https://vince59.github.io/basicGame/
 
@@ -33,26 +76,40 @@ pub struct Shape {
     y: f32,
     collided: bool,
     life: i32,
+    // position au pas précédent (pour interpoler l'affichage)
+    prev_x: f32,
+    prev_y: f32,
+    // déplacement en pixels par seconde (balles, ennemis en zigzag)
+    velocity: Vec2,
+    // type d'ennemi (index dans Archetypes)
+    kind: usize,
+    // forme de collision, relative à (x, y)
+    collider: Collider,
 }
 
 impl Shape {
-    fn collides_with(&self, other: &Self) -> bool {
-        self.circle().overlaps_rect(&other.rect())
+    fn save_position(&mut self) {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
     }
-    fn circle(&self) -> Circle {
-        Circle {
-            x: (self.x),
-            y: (self.y),
-            r: (self.size),
-        }
+    // position affichée entre le pas précédent et le pas courant
+    fn lerp_position(&self, alpha: f32) -> Vec2 {
+        vec2(
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+        )
     }
-    fn rect(&self) -> Rect {
-        Rect {
-            x: self.x - self.size / 2.0,
-            y: self.y - self.size / 2.0,
-            w: self.size,
-            h: self.size,
-        }
+    // test précis entre les formes de collision des deux objets
+    fn collides_with(&self, other: &Self) -> bool {
+        self.collider.overlaps(
+            vec2(self.x, self.y),
+            &other.collider,
+            vec2(other.x, other.y),
+        )
+    }
+    // rectangle englobant la forme de collision (pour la grille)
+    fn bounds(&self) -> Rect {
+        self.collider.bounds(vec2(self.x, self.y))
     }
 }
 enum GameState {
@@ -61,12 +118,9 @@ enum GameState {
     Paused,
     GameOver,
     Won,
-}
-
-enum Collision {
-    BulletEnemy,
-    ShipEnemy,
-    BuildingEnemy,
+    Replay,
+    Options,
+    Controls,
 }
 
 fn window_conf() -> Conf {
@@ -79,153 +133,543 @@ fn window_conf() -> Conf {
     }
 }
 
+fn continue_prompt(input_map: &InputMap) -> String {
+    format!(
+        "Press {} to continue ...",
+        input_map.key_name(Action::Confirm)
+    )
+}
+
+// caméra de l'affichage de la partie : la zone de jeu garde sa taille pendant toute la partie
+// (les replays en dépendent) et suit la fenêtre à l'échelle ; décalée pendant les secousses
+fn world_camera(world: &World, offset: Vec2) -> Camera2D {
+    let (width, height) = (screen_width(), screen_height());
+    let scale = (width / world.width).min(height / world.height);
+    Camera2D {
+        target: vec2(world.width / 2.0, world.height / 2.0) - offset,
+        zoom: vec2(2.0 * scale / width, 2.0 * scale / height),
+        ..Default::default()
+    }
+}
+
+// ville d'une partie ou d'une relecture : ses images sont chargées si besoin
+// et tout ce qui dépend de la ville est refait
+async fn switch_level(
+    file: &str,
+    world: &mut World,
+    assets: &mut Assets,
+    renderer: &mut Renderer,
+    music: &MusicList,
+) -> Result<(), data::DataError> {
+    let level = Level::read(file).await?;
+    let archetypes = &world.enemies.archetypes;
+    assets
+        .load_missing(&AssetManifest::new(&level, archetypes, music))
+        .await;
+    *renderer = Renderer::new(&level, archetypes, assets);
+    let masks = MaskLibrary::load(&level, archetypes, assets);
+    world.set_level(level);
+    world.set_masks(masks);
+    Ok(())
+}
+
+// musique de la partie : celle du boss pour la dernière vague
+fn game_music(world: &World) -> MusicState {
+    if world.waves.is_final_wave() {
+        MusicState::Boss
+    } else {
+        MusicState::Gameplay
+    }
+}
+
+// valeur d'une option de la ligne de commande (--seed 1234, --level city.ron, --stress 2000)
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     set_window_position(10, 10);
     set_pc_assets_folder("assets");
-    rand::srand(miniquad::date::now() as u64);
+    simulate_mouse_with_touch(true); // les boutons du menu répondent au doigt
+    rand::srand(miniquad::date::now() as u64); // aléatoire des effets uniquement (particules)
+    let mut settings = Settings::load();
+    if settings.fullscreen {
+        set_fullscreen(true);
+    }
+    let fixed_seed = arg_value("--seed").and_then(|seed| seed.parse::<u64>().ok());
+    // scène de test des collisions : --stress <nombre d'ennemis et de balles>
+    let stress = arg_value("--stress")
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+    let level_file = arg_value("--level").unwrap_or(DEFAULT_LEVEL_FILE.to_string());
+    let (level, level_error) = Level::load(&level_file).await;
+    let (archetypes, archetypes_error) = Archetypes::load(DEFAULT_ARCHETYPES_FILE).await;
+    let mut data_errors = vec![];
+    if let Some(e) = level_error {
+        error!("{}", e);
+        data_errors.push(format!("{} (using the default city)", e));
+    }
+    if let Some(e) = archetypes_error {
+        error!("{}", e);
+        data_errors.push(format!("{} (using the default enemies)", e));
+    }
+    let (waves, waves_error) = WaveList::load(DEFAULT_WAVES_FILE, &archetypes).await;
+    if let Some(e) = waves_error {
+        error!("{}", e);
+        data_errors.push(format!("{} (using the default waves)", e));
+    }
+    let (music_list, music_error) = MusicList::load(DEFAULT_MUSIC_FILE).await;
+    if let Some(e) = music_error {
+        error!("{}", e);
+        data_errors.push(format!("{} (using the default music)", e));
+    }
+    info!("city: {}", level.name);
 
     let mut game_state = GameState::MainMenu;
-    let mut bullets = BulletsSet::new().await;
-    let mut explosions = ExplosionsSet::new().await;
-    let mut enemies = EnemiesSet::new().await;
-    let mut buildings = BuildingsSet::new().await;
-    let mut ship = Ship::new().await;
-    let mut score = Score::new();
-    let mut menu = Menu::new().await;
-    build_textures_atlas();
-    let font = load_ttf_font("test.ttf").await.unwrap();
+    // toutes les images, sons et polices, chargés derrière l'écran de progression
+    let mut assets = Assets::load(&AssetManifest::new(&level, &archetypes, &music_list)).await;
+    let mut asset_errors: Vec<String> = assets.errors.iter().map(|e| e.to_string()).collect();
+    let mut renderer = Renderer::new(&level, &archetypes, &assets);
+    let masks = MaskLibrary::load(&level, &archetypes, &assets);
+    let mut world = World::new(
+        screen_width(),
+        screen_height(),
+        0,
+        level,
+        archetypes,
+        waves,
+        masks,
+        Score::load(),
+    );
+    let mut explosions = ExplosionsSet::new(&assets);
+    let mut sounds = SoundEffects::new(&assets).await;
+    let mut hud = Hud::new();
+    let mut achievements = Achievements::new();
+    let mut menu = Menu::new(&assets);
+    // rechargement à chaud des fichiers modifiés, sur ordinateur seulement
+    let mut watcher = (!cfg!(target_arch = "wasm32")).then(|| HotReload::new("assets"));
+    let mut reload_errors: Vec<String> = vec![];
+    let mut font = assets.font("test.ttf");
     let mut starfield = Shader::new();
-    let mut theme_music = Music::new().await;
+    let mut mixer = Mixer::new(&settings);
+    let mut music = Music::new(&assets, music_list);
+    // fichier de la ville chargée dans world (celle d'une relecture peut être différente)
+    let mut world_level = level_file.clone();
+    let mut recording = Replay::new(&world, settings.difficulty, &world_level, STEP);
+    let mut replay_player: Option<ReplayPlayer> = None;
+    let mut timestep = FixedTimestep::new();
+    let mut pending_input = InputState::default();
+    let mut show_debug = stress > 0;
+    let mut show_atlas = false;
+    let mut step_time = 0.0;
+    let mut backend = MacroquadBackend::new();
+    let mut controls = ControlsScreen::new();
+    let mut options = OptionsScreen::new();
 
     loop {
+        backend.update(&mut settings);
+        atlas::reset_texture_switches();
         clear_background(BLACK);
         starfield.display();
-        match game_state {
-            GameState::MainMenu => {
-                theme_music.stop();
-                let mut play = || {
-                    enemies.clear();
-                    bullets.clear();
-                    explosions.clear();
-                    buildings.reset();
-                    ship.reset();
-                    score.reset();
-                    theme_music.reset();
-                    game_state = GameState::Playing;
-                };
-                menu.display(&mut play);
+        if is_key_pressed(KeyCode::F3) {
+            show_debug = !show_debug;
+        }
+        if is_key_pressed(KeyCode::F4) {
+            show_atlas = !show_atlas;
+        }
+        if is_key_pressed(KeyCode::F11) {
+            settings.fullscreen = !settings.fullscreen;
+            set_fullscreen(settings.fullscreen);
+            settings.save();
+        }
+        if is_key_pressed(KeyCode::F8) {
+            settings.muted = !settings.muted;
+            mixer.apply(&settings);
+            settings.save();
+        }
+        let changed = watcher
+            .as_mut()
+            .map(|watcher| watcher.poll(get_frame_time()))
+            .unwrap_or_default();
+        if !changed.is_empty() {
+            info!("reloading {:?}", changed);
+            data_errors.clear();
+            reload_errors.clear();
+            let data_files = [
+                world_level.as_str(),
+                DEFAULT_ARCHETYPES_FILE,
+                DEFAULT_WAVES_FILE,
+            ];
+            let data_changed = changed
+                .iter()
+                .any(|file| data_files.contains(&file.as_str()));
+            if data_changed {
+                match reload_data(&world_level).await {
+                    Ok((level, archetypes, waves)) => {
+                        assets
+                            .load_missing(&AssetManifest::new(&level, &archetypes, &music.list))
+                            .await;
+                        world.reload(level, archetypes, waves);
+                    }
+                    Err(e) => reload_errors.push(e.to_string()),
+                }
             }
-            GameState::Playing => {
-                let delta_time = get_frame_time(); // temps passé depuis la dernière frame
-                // mise à jour des composants du jeux
-                ship.update(delta_time);
-                bullets.update(delta_time);
-                explosions.update();
-                enemies.update(delta_time);
-                buildings.update();
-                // affichages
-                enemies.display();
-                bullets.display();
-                score.display();
-                buildings.display();
-                explosions.display();
-
-                if is_key_pressed(KeyCode::Space) {
-                    if ship.nb_ammo > -1 {
-                        bullets.push(ship.shoot());
-                        ship.nb_ammo -= 1;
+            if changed.iter().any(|file| file == DEFAULT_MUSIC_FILE) {
+                match reload_music().await {
+                    Ok(list) => {
+                        assets
+                            .load_missing(&AssetManifest::new(
+                                &world.level,
+                                &world.enemies.archetypes,
+                                &list,
+                            ))
+                            .await;
+                        music.stop(&mut mixer);
+                        music = Music::new(&assets, list);
                     }
+                    Err(e) => reload_errors.push(e.to_string()),
                 }
-                if is_key_pressed(KeyCode::Escape) {
-                    game_state = GameState::Paused;
+            }
+            // fichiers du manifeste modifiés, et leur type
+            let mut kinds = vec![];
+            for file in &changed {
+                if let Some(kind) = assets.kind(file) {
+                    assets.reload(file).await;
+                    kinds.push(kind);
+                }
+            }
+            if changed.iter().any(|file| file.ends_with(".glsl"))
+                && let Err(e) = starfield.reload().await
+            {
+                reload_errors.extend(e.lines().map(|line| line.to_string()));
+            }
+            // seul ce qui dépend des fichiers modifiés est refait, la partie en cours continue
+            if data_changed || kinds.contains(&AssetKind::Texture) {
+                world.set_masks(MaskLibrary::load(
+                    &world.level,
+                    &world.enemies.archetypes,
+                    &assets,
+                ));
+                renderer = Renderer::new(&world.level, &world.enemies.archetypes, &assets);
+                renderer.update(&world);
+            }
+            if kinds.contains(&AssetKind::Particle) {
+                explosions.explosion_texture = assets.texture("explosion.png");
+            }
+            for file in &changed {
+                if assets.kind(file) == Some(AssetKind::Sound) {
+                    sounds.reload(&assets, file).await;
+                }
+            }
+            // les morceaux rechargés repartent au début
+            if changed.iter().any(|file| music.uses(file)) {
+                music.stop(&mut mixer);
+                music = Music::new(&assets, music.list.clone());
+            }
+            if kinds.contains(&AssetKind::Image) || kinds.contains(&AssetKind::Font) {
+                root_ui().pop_skin();
+                menu = Menu::new(&assets);
+                font = assets.font("test.ttf");
+            }
+            asset_errors = assets.errors.iter().map(|e| e.to_string()).collect();
+            for e in &reload_errors {
+                error!("{}", e);
+            }
+        }
+        match game_state {
+            GameState::MainMenu => {
+                music.set_state(MusicState::Menu);
+                display_errors(&data_errors);
+                let choice = menu.display(&settings.controls, &backend);
+                if choice.is_some() {
+                    sounds.click();
                 }
-                let mut collision_handler =
-                    |enemy: &mut Shape, shape: &mut Shape, collision: &Collision| {
-                        match collision {
-                            Collision::BulletEnemy => {
-                                enemy.collided = true;
-                                explosions.push(enemy); // on ajoute une explosion
-                                score.increase(enemy.size.round() as u32);
+                match choice {
+                    Some(MenuChoice::Play) => {
+                        // graine de la partie : --seed <n> pour rejouer une partie donnée
+                        let seed = fixed_seed.unwrap_or(miniquad::date::now() as u64);
+                        info!("seed: {}", seed);
+                        if world_level != level_file {
+                            let result = switch_level(
+                                &level_file,
+                                &mut world,
+                                &mut assets,
+                                &mut renderer,
+                                &music.list,
+                            )
+                            .await;
+                            match result {
+                                Ok(()) => world_level = level_file.clone(),
+                                Err(e) => error!("{}", e),
                             }
-                            Collision::ShipEnemy => {
-                                enemy.collided = true;
-                                shape.life -= 1;
-                                if shape.life == -1 {
-                                    game_state = GameState::GameOver;
+                            asset_errors = assets.errors.iter().map(|e| e.to_string()).collect();
+                        }
+                        // la zone de jeu prend la taille de la fenêtre, puis ne bouge plus
+                        world.set_bounds(screen_width(), screen_height());
+                        world.stress = stress;
+                        world.reset(seed, settings.difficulty);
+                        sounds.width = world.width;
+                        explosions.clear();
+                        hud.clear();
+                        achievements.new_game();
+                        recording = Replay::new(&world, settings.difficulty, &world_level, STEP);
+                        timestep.reset();
+                        pending_input = InputState::default();
+                        game_state = GameState::Playing;
+                    }
+                    Some(MenuChoice::Replay) => {
+                        // la relecture se joue dans la ville enregistrée
+                        let loaded = match Replay::load() {
+                            Ok(replay) if replay.level != world_level => {
+                                let result = switch_level(
+                                    &replay.level,
+                                    &mut world,
+                                    &mut assets,
+                                    &mut renderer,
+                                    &music.list,
+                                )
+                                .await;
+                                asset_errors =
+                                    assets.errors.iter().map(|e| e.to_string()).collect();
+                                match result {
+                                    Ok(()) => {
+                                        world_level = replay.level.clone();
+                                        Ok(replay)
+                                    }
+                                    Err(e) => Err(e.to_string()),
                                 }
                             }
-                            Collision::BuildingEnemy => {
-                                explosions.push(enemy); // on ajoute une explosion
-                                shape.life -= if shape.life > 0 { 1 } else { 0 };
-                                if shape.life == 0 {
-                                    shape.collided = true; // batiment touché
-                                }
+                            result => result.map_err(|e| e.to_string()),
+                        };
+                        match loaded {
+                            Ok(replay) => {
+                                explosions.clear();
+                                hud.clear();
+                                replay_player = Some(ReplayPlayer::new(replay, &mut world));
+                                sounds.width = world.width;
+                                game_state = GameState::Replay;
                             }
+                            Err(e) => error!("{}", e),
                         }
-                    };
-
-                // Collision avec une balle
-                for enemy in enemies.get_list() {
-                    bullets.collides_with(enemy, &Collision::BulletEnemy, &mut collision_handler);
+                    }
+                    Some(MenuChoice::Options) => game_state = GameState::Options,
+                    Some(MenuChoice::Quit) | None => {}
                 }
-
-                // Collision avec un bâtiment
-                for building in buildings.get_list() {
-                    enemies.collides_with(
-                        building,
-                        &Collision::BuildingEnemy,
-                        &mut collision_handler,
+            }
+            GameState::Playing => {
+                let delta_time = get_frame_time(); // temps passé depuis la dernière frame
+                music.set_state(game_music(&world));
+                music.set_threat(world.threat());
+                music.set_paused(false);
+                let mut input = settings.controls.read(&backend, settings.mouse_aim);
+                // le point visé passe des pixels de la fenêtre à ceux de la zone de jeu
+                let camera = world_camera(&world, Vec2::ZERO);
+                input.aim = input.aim.map(|aim| camera.screen_to_world(aim).round());
+                pending_input.latch(&input);
+                // mise à jour des composants du jeux, par pas fixes
+                let mut events = vec![];
+                let step_start = get_time();
+                for _ in 0..timestep.advance(delta_time) {
+                    recording.push(&pending_input);
+                    events.extend(world.step(STEP, &pending_input));
+                    pending_input.consume_presses();
+                    if events
+                        .iter()
+                        .any(|event| matches!(event, GameEvent::GameOver | GameEvent::Won))
+                    {
+                        break;
+                    }
+                }
+                step_time = get_time() - step_start;
+                renderer.update(&world);
+                explosions.update();
+                hud.update(delta_time);
+                achievements.update(delta_time);
+                // pas d'effets dans la scène de test : il y aurait des centaines d'explosions par seconde
+                if world.stress == 0 {
+                    publish(
+                        &events,
+                        &mut [&mut sounds, &mut explosions, &mut hud, &mut achievements],
                     );
                 }
-
-                // collision avec le vaisseau
-                enemies.collides_with(
-                    ship.get_shape(),
-                    &Collision::ShipEnemy,
-                    &mut collision_handler,
-                );
-
-                //Tous les batiments détruits ?
-                if buildings.all_destroyed() {
-                    game_state = GameState::GameOver;
+                for event in &events {
+                    match event {
+                        GameEvent::GameOver => game_state = GameState::GameOver,
+                        GameEvent::Won => game_state = GameState::Won,
+                        GameEvent::WaveCleared(summary) => info!(
+                            "level {} wave {} cleared: {}/{} destroyed, +{}",
+                            summary.level,
+                            summary.wave,
+                            summary.destroyed,
+                            summary.count,
+                            summary.score
+                        ),
+                        _ => {}
+                    }
                 }
-
-                //Tous les ennemis détruits ?
-                if enemies.all_destroyed() {
-                    game_state = GameState::Won;
+                // partie terminée : on garde l'enregistrement pour la relecture
+                if matches!(game_state, GameState::GameOver | GameState::Won)
+                    && let Err(e) = recording.save()
+                {
+                    error!("{}", e);
                 }
+                if input.pause {
+                    game_state = GameState::Paused;
+                }
+                // affichages
+                let offset = hud.shake_offset(settings.screen_shake);
+                set_camera(&world_camera(&world, offset));
+                renderer.display(&world, timestep.alpha());
+                explosions.display();
+                if let Some(aim) = input.aim {
+                    renderer.display_crosshair(aim);
+                }
+                set_default_camera();
+                display_wave(font.as_ref(), &world.waves);
+                hud.display(font.as_ref());
+                achievements.display(font.as_ref());
+                backend.touch.display(settings.auto_fire);
             }
             GameState::Paused => {
-                theme_music.stop();
-                if is_key_pressed(KeyCode::Space) {
-                    theme_music.play();
+                music.set_paused(true);
+                if settings.controls.is_pressed(Action::Pause, &backend)
+                    || settings.controls.is_confirmed(&backend)
+                {
+                    timestep.reset();
+                    pending_input = InputState::default();
                     game_state = GameState::Playing;
                 }
-                enemies.display();
-                ship.display();
-                bullets.display();
-                score.display();
-                buildings.display();
+                set_camera(&world_camera(&world, Vec2::ZERO));
+                renderer.display(&world, timestep.alpha());
+                set_default_camera();
                 display_paused();
                 display_game_name();
             }
             GameState::GameOver => {
-                // Redémarrage du jeu si on presse espace
-                if is_key_pressed(KeyCode::LeftShift) {
+                music.set_state(MusicState::GameOver);
+                // Retour au menu si on valide
+                if settings.controls.is_confirmed(&backend) {
                     game_state = GameState::MainMenu;
                 }
-                display_game_over(&font);
-                score.display_high_score(&font);
+                display_game_over(font.as_ref(), &continue_prompt(&settings.controls));
+                world.score.display_high_score(font.as_ref());
             }
             GameState::Won => {
-                // Redémarrage du jeu si on presse espace
-                if is_key_pressed(KeyCode::LeftShift) {
+                music.set_state(MusicState::Victory);
+                // Retour au menu si on valide
+                if settings.controls.is_confirmed(&backend) {
                     game_state = GameState::MainMenu;
                 }
-                display_won(&font);
-                score.display_high_score(&font);
+                display_won(font.as_ref(), &continue_prompt(&settings.controls));
+                world.score.display_high_score(font.as_ref());
             }
+            GameState::Replay => {
+                if let Some(player) = replay_player.as_mut() {
+                    music.set_state(game_music(&world));
+                    music.set_threat(world.threat());
+                    player.handle_keys(&mut world, &settings.controls, &backend);
+                    let events = player.update(&mut world, get_frame_time());
+                    renderer.update(&world);
+                    explosions.update();
+                    hud.update(get_frame_time());
+                    // les succès ne se gagnent pas en relecture
+                    publish(&events, &mut [&mut sounds, &mut explosions, &mut hud]);
+                    let offset = hud.shake_offset(settings.screen_shake);
+                    set_camera(&world_camera(&world, offset));
+                    renderer.display(&world, player.alpha());
+                    explosions.display();
+                    set_default_camera();
+                    display_wave(font.as_ref(), &world.waves);
+                    hud.display(font.as_ref());
+                    player.display(&settings.controls);
+                }
+                if settings.controls.is_pressed(Action::Back, &backend) {
+                    replay_player = None;
+                    world.score = Score::load(); // on oublie le score de la relecture
+                    game_state = GameState::MainMenu;
+                }
+            }
+            GameState::Options => {
+                let choice = options.display(&mut settings, &backend);
+                mixer.apply(&settings);
+                if choice.is_some() {
+                    sounds.click();
+                }
+                match choice {
+                    Some(OptionsChoice::Controls) => game_state = GameState::Controls,
+                    Some(OptionsChoice::Back) => game_state = GameState::MainMenu,
+                    None => {}
+                }
+            }
+            GameState::Controls => {
+                if controls.update(&mut settings, &backend) {
+                    game_state = GameState::Options;
+                }
+                controls.display(&settings, &backend);
+            }
+        }
+        sounds.flush(&mut mixer);
+        music.update(get_frame_time(), &mut mixer);
+        mixer.update(get_frame_time());
+        // changements de page de sprites dans la frame, relevés avant les affichages de mise au point
+        let texture_switches = atlas::texture_switches();
+        if show_atlas {
+            assets.atlas.display_pages();
+        }
+        display_asset_warnings(&asset_errors);
+        display_errors(&reload_errors);
+        if show_debug {
+            let steps = match (&game_state, &replay_player) {
+                (GameState::Replay, Some(player)) => player.steps(),
+                _ => timestep.steps,
+            };
+            draw_text(
+                &format!(
+                    "fps: {}  steps/frame: {}  sprite texture switches: {}",
+                    get_fps(),
+                    steps,
+                    texture_switches
+                ),
+                10.0,
+                60.0,
+                20.0,
+                GREEN,
+            );
+            // coût des collisions : tests faits grâce à la grille, contre tous les couples
+            let enemies = world.enemies.enemies.len();
+            let all_pairs =
+                enemies * (world.bullets.bullets.len() + world.buildings.buildings.len() + 1);
+            draw_text(
+                &format!(
+                    "simulation: {:.2} ms  enemies: {}  bullets: {}  tests: {} / {}",
+                    step_time * 1000.0,
+                    enemies,
+                    world.bullets.bullets.len(),
+                    world.collision_tests,
+                    all_pairs
+                ),
+                10.0,
+                80.0,
+                20.0,
+                GREEN,
+            );
+            draw_text(
+                &format!(
+                    "sound voices: {}  coalesced: {}  stolen: {}",
+                    mixer.active_voices(),
+                    mixer.coalesced,
+                    mixer.stolen
+                ),
+                10.0,
+                100.0,
+                20.0,
+                GREEN,
+            );
         }
         next_frame().await
     }
@@ -0,0 +1,123 @@
+/* Structure MaskLibrary (masques d'opacité des sprites, pour les collisions au pixel près) */
+
+use crate::archetypes::Archetypes;
+use crate::assets::Assets;
+use crate::collider::ColliderDef;
+use crate::level::Level;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// masque d'une image de la planche : un booléen par pixel
+pub struct Mask {
+    width: u32,
+    height: u32,
+    bits: Vec<bool>,
+}
+
+// masques de toutes les images d'une planche, rangés ligne par ligne
+pub struct SheetMasks {
+    pub frame_width: u32,
+    pub frame_height: u32,
+    columns: u32,
+    masks: Vec<Mask>,
+}
+
+// masques des planches dont la forme de collision est Mask, par nom de fichier
+#[derive(Clone)]
+pub struct MaskLibrary {
+    sheets: HashMap<String, Rc<SheetMasks>>,
+}
+
+impl Mask {
+    // pixel plein ; en dehors de l'image tout est vide
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as u32) < self.width
+            && (y as u32) < self.height
+            && self.bits[(y as u32 * self.width + x as u32) as usize]
+    }
+}
+
+impl SheetMasks {
+    // un pixel est plein s'il est opaque et pas noir : les planches de la ville ont un
+    // fond noir opaque, invisible sur le fond du jeu
+    pub fn from_image(image: &Image, frame_width: u32, frame_height: u32) -> SheetMasks {
+        let columns = (image.width as u32 / frame_width).max(1);
+        let rows = (image.height as u32 / frame_height).max(1);
+        let data = image.get_image_data();
+        let mut masks = vec![];
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut bits = Vec::with_capacity((frame_width * frame_height) as usize);
+                for y in 0..frame_height {
+                    for x in 0..frame_width {
+                        let (px, py) = (column * frame_width + x, row * frame_height + y);
+                        let solid = px < image.width as u32
+                            && py < image.height as u32
+                            && matches!(
+                                data[(py * image.width as u32 + px) as usize],
+                                [r, g, b, a] if a >= 128 && (r, g, b) != (0, 0, 0)
+                            );
+                        bits.push(solid);
+                    }
+                }
+                masks.push(Mask {
+                    width: frame_width,
+                    height: frame_height,
+                    bits,
+                });
+            }
+        }
+        SheetMasks {
+            frame_width,
+            frame_height,
+            columns,
+            masks,
+        }
+    }
+
+    // None si l'image demandée n'est pas dans la planche (taille d'image fausse dans les données)
+    pub fn get(&self, row: u32, frame: u32) -> Option<&Mask> {
+        if frame >= self.columns {
+            return None;
+        }
+        self.masks.get((row * self.columns + frame) as usize)
+    }
+}
+
+impl MaskLibrary {
+    pub fn new() -> MaskLibrary {
+        MaskLibrary {
+            sheets: HashMap::new(),
+        }
+    }
+
+    // calcul des masques des bâtiments et des ennemis qui en demandent un
+    pub fn load(level: &Level, archetypes: &Archetypes, assets: &Assets) -> MaskLibrary {
+        let mut library = MaskLibrary::new();
+        let sheets = level
+            .buildings
+            .iter()
+            .map(|def| (&def.texture, def.width, def.height, &def.collider))
+            .chain(
+                archetypes
+                    .list
+                    .iter()
+                    .map(|a| (&a.def.texture, a.def.width, a.def.height, &a.def.collider)),
+            );
+        for (texture, width, height, collider) in sheets {
+            if !matches!(collider, ColliderDef::Mask) || library.sheets.contains_key(texture) {
+                continue;
+            }
+            let masks = SheetMasks::from_image(assets.image(texture), width, height);
+            library.sheets.insert(texture.clone(), Rc::new(masks));
+        }
+        library
+    }
+
+    pub fn get(&self, texture: &str) -> Option<Rc<SheetMasks>> {
+        self.sheets.get(texture).cloned()
+    }
+}
@@ -1,42 +1,61 @@
 /* Structure Menu (gestion du menu) */
 
+use crate::assets::Assets;
+use crate::input::*;
 use macroquad::prelude::*;
-use macroquad::ui::{Skin, hash, root_ui};
+use macroquad::ui::{Skin, StyleBuilder, hash, root_ui};
+
+// choix fait dans le menu
+#[derive(Clone, Copy)]
+pub enum MenuChoice {
+    Play,
+    Replay,
+    Options,
+    Quit,
+}
+
+// pas de "Quit" dans le navigateur : on ne ferme pas l'onglet depuis le jeu
+const ITEMS: &[(&str, MenuChoice)] = if cfg!(target_arch = "wasm32") {
+    &[
+        ("Play", MenuChoice::Play),
+        ("Replay", MenuChoice::Replay),
+        ("Options", MenuChoice::Options),
+    ]
+} else {
+    &[
+        ("Play", MenuChoice::Play),
+        ("Replay", MenuChoice::Replay),
+        ("Options", MenuChoice::Options),
+        ("Quit", MenuChoice::Quit),
+    ]
+};
 
 pub struct Menu {
     pub window_size: Vec2,
+    selected: usize,
 }
 
 impl Menu {
-    pub async fn new() -> Menu {
-        let window_background = load_image("window_background.png").await.unwrap();
-        let button_background = load_image("button_background.png").await.unwrap();
-        let button_clicked_background = load_image("button_clicked_background.png").await.unwrap();
-        let font = load_file("atari_games.ttf").await.unwrap();
+    pub fn new(assets: &Assets) -> Menu {
+        let window_background = assets.image("window_background.png").clone();
+        let button_background = assets.image("button_background.png").clone();
+        let button_clicked_background = assets.image("button_clicked_background.png").clone();
+        let font = assets.font_bytes("atari_games.ttf");
         let window_style = root_ui()
             .style_builder()
             .background(window_background)
             .background_margin(RectOffset::new(32.0, 76.0, 44.0, 20.0))
             .margin(RectOffset::new(0.0, -40.0, 0.0, 0.0))
             .build();
-        let button_style = root_ui()
-            .style_builder()
+        let button_style = style_builder(font)
             .background(button_background)
             .background_clicked(button_clicked_background)
             .background_margin(RectOffset::new(16.0, 16.0, 16.0, 16.0))
             .margin(RectOffset::new(16.0, 0.0, -8.0, -8.0))
-            .font(&font)
-            .unwrap()
             .text_color(WHITE)
             .font_size(64)
             .build();
-        let label_style = root_ui()
-            .style_builder()
-            .font(&font)
-            .unwrap()
-            .text_color(WHITE)
-            .font_size(28)
-            .build();
+        let label_style = style_builder(font).text_color(WHITE).font_size(28).build();
         let ui_skin = Skin {
             window_style,
             button_style,
@@ -44,15 +63,31 @@ impl Menu {
             ..root_ui().default_skin()
         };
         root_ui().push_skin(&ui_skin);
-        let window_size: Vec2 = vec2(370.0, 320.0);
+        let window_size: Vec2 = vec2(370.0, 520.0);
 
-        Menu { window_size }
+        Menu {
+            window_size,
+            selected: 0,
+        }
     }
 
-    pub fn display<F>(&mut self, play: &mut F)
-    where
-        F: FnMut(),
-    {
+    pub fn display(
+        &mut self,
+        input_map: &InputMap,
+        backend: &dyn InputBackend,
+    ) -> Option<MenuChoice> {
+        let mut choice = None;
+        // navigation au clavier ou à la croix de la manette
+        if input_map.is_pressed(Action::MoveUp, backend) {
+            self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+        }
+        if input_map.is_pressed(Action::MoveDown, backend) {
+            self.selected = (self.selected + 1) % ITEMS.len();
+        }
+        if input_map.is_pressed(Action::Confirm, backend) {
+            choice = Some(ITEMS[self.selected].1);
+        }
+        let selected = self.selected;
         root_ui().window(
             hash!(),
             vec2(
@@ -62,13 +97,26 @@ impl Menu {
             self.window_size,
             |ui| {
                 ui.label(vec2(80.0, -34.0), "Main Menu");
-                if ui.button(vec2(65.0, 25.0), "Play") {
-                    play();
-                }
-                if ui.button(vec2(65.0, 125.0), "Quit") {
-                    std::process::exit(0);
+                for (i, (label, item)) in ITEMS.iter().enumerate() {
+                    let y = 25.0 + i as f32 * 100.0;
+                    if i == selected {
+                        ui.label(vec2(25.0, y + 20.0), ">");
+                    }
+                    if ui.button(vec2(65.0, y), *label) {
+                        choice = Some(*item);
+                    }
                 }
             },
         );
+        if let Some(MenuChoice::Quit) = choice {
+            std::process::exit(0);
+        }
+        choice
     }
 }
+
+// style avec la police du menu, ou la police par défaut si elle n'a pas pu être chargée
+fn style_builder(font: Option<&[u8]>) -> StyleBuilder {
+    font.and_then(|font| root_ui().style_builder().font(font).ok())
+        .unwrap_or_else(|| root_ui().style_builder())
+}
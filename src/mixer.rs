@@ -0,0 +1,482 @@
+/* Structure Mixer (bus de mixage musique, bruitages et interface, volume général, priorités et voix) */
+
+use crate::settings::Settings;
+use crate::sound_variants::Variant;
+use macroquad::audio::{PlaySoundParams, Sound, play_sound, set_sound_volume, stop_sound};
+
+// nombre maximal de sons lancés dans une même frame, les plus prioritaires d'abord
+const MAX_SOUNDS_PER_FRAME: usize = 6;
+// baisse de la musique pendant les grosses explosions : niveau, durée, puis remontée
+const DUCK_LEVEL: f32 = 0.35;
+const DUCK_HOLD: f32 = 0.4;
+const DUCK_RELEASE: f32 = 0.6;
+// deux demandes du même son à moins de COALESCE_WINDOW secondes n'en font qu'une
+const COALESCE_WINDOW: f64 = 0.05;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+// quand trop de sons partent ensemble, les moins importants sont abandonnés
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+// son demandé pendant la frame, lancé par update
+pub struct SoundRequest {
+    pub variant: Variant,
+    // nom du son : ses versions partagent le même nombre de voix
+    pub group: &'static str,
+    // nombre maximal de voix du son jouées en même temps
+    pub voices: usize,
+    pub bus: Bus,
+    pub volume: f32,
+    pub priority: Priority,
+}
+
+// son en train de jouer, d'après sa durée (macroquad ne dit pas quand un son se termine) ;
+// sound est le son à couper si la voix est volée
+struct Voice<S> {
+    group: &'static str,
+    variant: usize,
+    sound: S,
+    volume: f32,
+    priority: Priority,
+    start: f64,
+    end: f64,
+}
+
+// ce que le limiteur de voix doit savoir d'une demande
+struct VoiceRequest<S> {
+    group: &'static str,
+    variant: usize,
+    sound: S,
+    limit: usize,
+    volume: f32,
+    priority: Priority,
+    duration: f32,
+}
+
+// demandes retenues pour une frame et voix à couper pour leur faire de la place
+struct Schedule<S> {
+    // index des demandes à lancer
+    play: Vec<usize>,
+    stop: Vec<S>,
+    coalesced: u32,
+}
+
+// place trouvée pour une nouvelle voix
+enum Room<S> {
+    Free,
+    // en coupant ce son
+    Steal(S),
+    Full,
+}
+
+// comptes des voix en cours, sans rien jouer ni couper : Mixer applique le résultat
+struct VoicePool<S> {
+    voices: Vec<Voice<S>>,
+    // horloge du mixer, en secondes
+    time: f64,
+}
+
+// morceau qui boucle sur le bus de la musique
+struct Track {
+    name: String,
+    sound: Sound,
+    volume: f32,
+    // volume réellement appliqué, pour ne le changer que s'il bouge
+    applied: f32,
+}
+
+pub struct Mixer {
+    master: f32,
+    music: f32,
+    sfx: f32,
+    ui: f32,
+    muted: bool,
+    // temps écoulé depuis la dernière baisse de la musique
+    duck: f32,
+    tracks: Vec<Track>,
+    queue: Vec<SoundRequest>,
+    voices: VoicePool<Sound>,
+    // demandes fusionnées et voix coupées depuis le lancement (mise au point)
+    pub coalesced: u32,
+    pub stolen: u32,
+}
+
+impl Mixer {
+    pub fn new(settings: &Settings) -> Mixer {
+        let mut mixer = Mixer {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+            ui: 1.0,
+            muted: false,
+            duck: DUCK_HOLD + DUCK_RELEASE,
+            tracks: vec![],
+            queue: vec![],
+            voices: VoicePool::new(),
+            coalesced: 0,
+            stolen: 0,
+        };
+        mixer.apply(settings);
+        mixer
+    }
+
+    // volumes des réglages du joueur, pris en compte dès la frame suivante
+    pub fn apply(&mut self, settings: &Settings) {
+        self.master = settings.master_volume;
+        self.music = settings.music_volume;
+        self.sfx = settings.sfx_volume;
+        self.ui = settings.ui_volume;
+        self.muted = settings.muted;
+    }
+
+    // volume d'un bus, volume général compris
+    fn gain(&self, bus: Bus) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let bus = match bus {
+            Bus::Music => self.music * self.ducking(),
+            Bus::Sfx => self.sfx,
+            Bus::Ui => self.ui,
+        };
+        self.master * bus
+    }
+
+    // la musique reste baissée DUCK_HOLD secondes puis remonte progressivement
+    fn ducking(&self) -> f32 {
+        let release = ((self.duck - DUCK_HOLD) / DUCK_RELEASE).clamp(0.0, 1.0);
+        DUCK_LEVEL + (1.0 - DUCK_LEVEL) * release
+    }
+
+    // baisse de la musique, pour laisser entendre une grosse explosion
+    pub fn duck(&mut self) {
+        self.duck = 0.0;
+    }
+
+    pub fn play(&mut self, request: SoundRequest) {
+        self.queue.push(request);
+    }
+
+    // voix en cours, pour l'affichage de mise au point
+    pub fn active_voices(&self) -> usize {
+        self.voices.voices.len()
+    }
+
+    // morceau en boucle, repéré par son nom ; sans effet s'il joue déjà
+    pub fn play_music(&mut self, name: &str, sound: &Sound, volume: f32) {
+        if self.tracks.iter().any(|track| track.name == name) {
+            return;
+        }
+        let applied = volume * self.gain(Bus::Music);
+        play_sound(
+            sound,
+            PlaySoundParams {
+                looped: true,
+                volume: applied,
+            },
+        );
+        self.tracks.push(Track {
+            name: name.to_string(),
+            sound: sound.clone(),
+            volume,
+            applied,
+        });
+    }
+
+    // volume propre d'un morceau (fondus), appliqué par update
+    pub fn set_music_volume(&mut self, name: &str, volume: f32) {
+        if let Some(track) = self.tracks.iter_mut().find(|track| track.name == name) {
+            track.volume = volume;
+        }
+    }
+
+    pub fn stop_music(&mut self, name: &str) {
+        self.tracks.retain(|track| {
+            if track.name == name {
+                stop_sound(&track.sound);
+            }
+            track.name != name
+        });
+    }
+
+    // à appeler une fois par frame, après les événements de la partie
+    pub fn update(&mut self, delta_time: f32) {
+        self.duck += delta_time;
+        self.voices.advance(delta_time);
+        let gain = self.gain(Bus::Music);
+        for track in self.tracks.iter_mut() {
+            let volume = track.volume * gain;
+            if volume != track.applied {
+                set_sound_volume(&track.sound, volume);
+                track.applied = volume;
+            }
+        }
+        let queue = std::mem::take(&mut self.queue);
+        let requests: Vec<VoiceRequest<Sound>> = queue
+            .iter()
+            .map(|request| VoiceRequest {
+                group: request.group,
+                variant: request.variant.index,
+                sound: request.variant.sound.clone(),
+                limit: request.voices,
+                volume: request.volume,
+                priority: request.priority,
+                duration: request.variant.duration,
+            })
+            .collect();
+        let schedule = self.voices.schedule(requests);
+        self.coalesced += schedule.coalesced;
+        self.stolen += schedule.stop.len() as u32;
+        for sound in &schedule.stop {
+            stop_sound(sound);
+        }
+        // le volume est partagé entre les sons lancés ensemble pour ne pas saturer
+        let accepted: Vec<&SoundRequest> = schedule.play.iter().map(|&i| &queue[i]).collect();
+        let share = 1.0 / (accepted.len().max(1) as f32).sqrt();
+        for request in accepted {
+            let volume = request.volume * self.gain(request.bus) * share;
+            if volume > 0.0 {
+                play_sound(
+                    &request.variant.sound,
+                    PlaySoundParams {
+                        looped: false,
+                        volume,
+                    },
+                );
+            }
+        }
+    }
+}
+
+impl<S: Clone> VoicePool<S> {
+    fn new() -> VoicePool<S> {
+        VoicePool {
+            voices: vec![],
+            time: 0.0,
+        }
+    }
+
+    // avance l'horloge et oublie les voix terminées
+    fn advance(&mut self, delta_time: f32) {
+        self.time += delta_time as f64;
+        let time = self.time;
+        self.voices.retain(|voice| voice.end > time);
+    }
+
+    // demandes d'une frame : les plus prioritaires d'abord, dans l'ordre des demandes à
+    // priorité égale, au plus MAX_SOUNDS_PER_FRAME ; les voix retenues sont comptées
+    fn schedule(&mut self, requests: Vec<VoiceRequest<S>>) -> Schedule<S> {
+        let mut order: Vec<usize> = (0..requests.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(requests[i].priority));
+        let mut schedule = Schedule {
+            play: vec![],
+            stop: vec![],
+            coalesced: 0,
+        };
+        for i in order {
+            if schedule.play.len() == MAX_SOUNDS_PER_FRAME {
+                break;
+            }
+            let request = &requests[i];
+            if self.coalesce(request) {
+                schedule.coalesced += 1;
+                continue;
+            }
+            match self.make_room(request) {
+                Room::Free => {}
+                Room::Steal(sound) => schedule.stop.push(sound),
+                Room::Full => continue,
+            }
+            self.voices.push(Voice {
+                group: request.group,
+                variant: request.variant,
+                sound: request.sound.clone(),
+                volume: request.volume,
+                priority: request.priority,
+                start: self.time,
+                end: self.time + request.duration as f64,
+            });
+            schedule.play.push(i);
+        }
+        schedule
+    }
+
+    // une voix du même son, lancée à l'instant et au moins aussi importante, suffit
+    fn coalesce(&self, request: &VoiceRequest<S>) -> bool {
+        self.voices.iter().any(|voice| {
+            voice.group == request.group
+                && voice.priority >= request.priority
+                && self.time - voice.start < COALESCE_WINDOW
+        })
+    }
+
+    // place pour une voix de plus : au-delà du nombre permis, la voix la plus faible
+    // (la plus ancienne à volume égal) est coupée, si elle n'est pas plus forte que la nouvelle
+    fn make_room(&mut self, request: &VoiceRequest<S>) -> Room<S> {
+        let playing = self
+            .voices
+            .iter()
+            .filter(|voice| voice.group == request.group);
+        if playing.clone().count() < request.limit {
+            return Room::Free;
+        }
+        let Some(victim) = playing.min_by(|a, b| {
+            a.volume
+                .total_cmp(&b.volume)
+                .then(a.start.total_cmp(&b.start))
+        }) else {
+            return Room::Full;
+        };
+        if victim.volume > request.volume {
+            return Room::Full;
+        }
+        // arrêter une version arrête toutes ses voix
+        let (group, variant, sound) = (victim.group, victim.variant, victim.sound.clone());
+        self.voices
+            .retain(|voice| voice.group != group || voice.variant != variant);
+        Room::Steal(sound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // les sons sont remplacés par un numéro
+    fn request(
+        group: &'static str,
+        sound: u32,
+        volume: f32,
+        priority: Priority,
+    ) -> VoiceRequest<u32> {
+        VoiceRequest {
+            group,
+            variant: sound as usize,
+            sound,
+            limit: 2,
+            volume,
+            priority,
+            duration: 1.0,
+        }
+    }
+
+    // une demande par frame, assez espacées pour ne pas être fusionnées
+    fn play_apart(
+        pool: &mut VoicePool<u32>,
+        requests: Vec<VoiceRequest<u32>>,
+    ) -> Vec<Schedule<u32>> {
+        requests
+            .into_iter()
+            .map(|request| {
+                pool.advance(0.1);
+                pool.schedule(vec![request])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn group_over_its_limit_steals_the_oldest_voice() {
+        let mut pool = VoicePool::new();
+        let schedules = play_apart(
+            &mut pool,
+            vec![
+                request("laser", 1, 1.0, Priority::Normal),
+                request("laser", 2, 1.0, Priority::Normal),
+                request("explosion", 3, 1.0, Priority::Normal),
+                request("laser", 4, 1.0, Priority::Normal),
+            ],
+        );
+        // les deux premiers tirs et l'explosion ont de la place
+        assert!(
+            schedules[..3]
+                .iter()
+                .all(|s| s.play == [0] && s.stop.is_empty())
+        );
+        assert_eq!(schedules[3].play, [0]);
+        assert_eq!(schedules[3].stop, [1]);
+        assert_eq!(pool.voices.len(), 3);
+    }
+
+    #[test]
+    fn quietest_voice_is_stolen_and_louder_ones_are_kept() {
+        let mut pool = VoicePool::new();
+        let schedules = play_apart(
+            &mut pool,
+            vec![
+                request("explosion", 1, 0.8, Priority::Low),
+                request("explosion", 2, 0.3, Priority::Low),
+                request("explosion", 3, 0.5, Priority::Low),
+                // plus faible que toutes les voix en cours : abandonnée
+                request("explosion", 4, 0.4, Priority::Low),
+            ],
+        );
+        assert_eq!(schedules[2].stop, [2]);
+        assert!(schedules[3].play.is_empty());
+        assert!(schedules[3].stop.is_empty());
+        let playing: Vec<u32> = pool.voices.iter().map(|voice| voice.sound).collect();
+        assert_eq!(playing, [1, 3]);
+    }
+
+    #[test]
+    fn close_requests_of_the_same_sound_are_coalesced() {
+        let mut pool = VoicePool::new();
+        let schedule = pool.schedule(vec![
+            request("laser", 1, 1.0, Priority::Normal),
+            request("laser", 2, 1.0, Priority::Normal),
+            request("explosion", 3, 1.0, Priority::Normal),
+        ]);
+        assert_eq!((schedule.play, schedule.coalesced), (vec![0, 2], 1));
+        // encore dans la fenêtre, sauf pour un son plus important
+        pool.advance((COALESCE_WINDOW / 2.0) as f32);
+        let schedule = pool.schedule(vec![
+            request("laser", 4, 1.0, Priority::Normal),
+            request("explosion", 5, 1.0, Priority::High),
+        ]);
+        assert_eq!((schedule.play, schedule.coalesced), (vec![1], 1));
+        // après la fenêtre, le son est rejoué
+        pool.advance(COALESCE_WINDOW as f32);
+        let schedule = pool.schedule(vec![request("laser", 6, 1.0, Priority::Normal)]);
+        assert_eq!((schedule.play, schedule.coalesced), (vec![0], 0));
+    }
+
+    #[test]
+    fn frame_keeps_the_most_important_sounds() {
+        const GROUPS: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let mut pool = VoicePool::new();
+        let requests = GROUPS
+            .iter()
+            .enumerate()
+            .map(|(i, group)| {
+                let priority = if i >= 6 {
+                    Priority::High
+                } else {
+                    Priority::Low
+                };
+                request(group, i as u32, 1.0, priority)
+            })
+            .collect();
+        let schedule = pool.schedule(requests);
+        assert_eq!(schedule.play, [6, 7, 0, 1, 2, 3]);
+        assert_eq!(schedule.play.len(), MAX_SOUNDS_PER_FRAME);
+    }
+
+    #[test]
+    fn finished_voices_free_their_place() {
+        let mut pool = VoicePool::new();
+        pool.schedule(vec![request("laser", 1, 1.0, Priority::Normal)]);
+        pool.advance(0.5);
+        assert_eq!(pool.voices.len(), 1);
+        pool.advance(0.6);
+        assert!(pool.voices.is_empty());
+    }
+}
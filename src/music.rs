@@ -1,30 +1,370 @@
-use macroquad::audio::{PlaySoundParams, Sound, load_sound, play_sound, stop_sound};
+/* Structure Music (morceaux par état du jeu, fondus enchaînés et couches selon la menace) */
+
+use crate::assets::Assets;
+use crate::data::*;
+use crate::mixer::Mixer;
+use macroquad::audio::Sound;
+use nanoserde::DeRon;
+use std::collections::HashMap;
+
+pub const DEFAULT_MUSIC_FILE: &str = "music.ron";
+// les musiques livrées avec le jeu
+const DEFAULT_MUSIC: &str = include_str!("../assets/music.ron");
+// volume de la musique de la partie pendant la pause
+const PAUSED_LEVEL: f32 = 0.3;
+
+// une couche de la musique de la partie et la menace à partir de laquelle elle joue
+#[derive(DeRon, Clone)]
+pub struct StemDef {
+    pub file: String,
+    pub threat: f32,
+}
+
+#[derive(DeRon, Clone)]
+pub struct MusicList {
+    pub crossfade: f32,
+    pub menu: Vec<String>,
+    pub gameplay: Vec<StemDef>,
+    pub boss: Vec<String>,
+    pub game_over: Vec<String>,
+    pub victory: Vec<String>,
+}
+
+impl MusicList {
+    // lecture et vérification d'un fichier de musiques
+    pub fn parse(file: &str, text: &str) -> Result<MusicList, DataError> {
+        let list =
+            MusicList::deserialize_ron(text).map_err(|e| DataError::Parse(file.to_string(), e))?;
+        let invalid = |entry: usize, name: &str, message: &str| DataError::Invalid {
+            file: file.to_string(),
+            entry,
+            name: name.to_string(),
+            message: message.to_string(),
+        };
+        if !list.crossfade.is_finite() || list.crossfade < 0.0 {
+            return Err(invalid(0, "crossfade", "must be a positive number"));
+        }
+        for (i, stem) in list.gameplay.iter().enumerate() {
+            if !(0.0..=1.0).contains(&stem.threat) {
+                return Err(invalid(i, &stem.file, "threat must be between 0 and 1"));
+            }
+        }
+        if let Some(i) = list.files().iter().position(|file| file.is_empty()) {
+            return Err(invalid(i, "", "file name is empty"));
+        }
+        Ok(list)
+    }
+
+    pub fn default_music() -> MusicList {
+        MusicList::parse(DEFAULT_MUSIC_FILE, DEFAULT_MUSIC).expect("invalid built-in music file")
+    }
+
+    // chargement depuis le dossier assets ; en cas d'erreur on garde les musiques par défaut
+    pub async fn load(file: &str) -> (MusicList, Option<DataError>) {
+        load_or_default(
+            file,
+            |text| MusicList::parse(file, text),
+            MusicList::default_music,
+        )
+        .await
+    }
+
+    // tous les fichiers cités, pour le manifeste des ressources
+    pub fn files(&self) -> Vec<&str> {
+        let stems = self.gameplay.iter().map(|stem| &stem.file);
+        self.menu
+            .iter()
+            .chain(stems)
+            .chain(&self.boss)
+            .chain(&self.game_over)
+            .chain(&self.victory)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MusicState {
+    Menu,
+    Gameplay,
+    Boss,
+    GameOver,
+    Victory,
+}
+
 pub struct Music {
-    theme_music: Sound,
+    pub list: MusicList,
+    sounds: HashMap<String, Sound>,
+    state: Option<MusicState>,
+    // rang du morceau joué dans la liste de chaque état, avancé à chaque entrée dans l'état
+    turns: HashMap<String, usize>,
+    // menace de la partie, entre 0 et 1
+    threat: f32,
+    // pause : la musique de l'état continue, moins fort
+    paused: bool,
+    // fichiers en cours et leur volume dans le fondu
+    volumes: Vec<(String, f32)>,
+    // entrée dans la partie : les couches repartent ensemble du début (voir update)
+    restart: bool,
 }
 
 impl Music {
-    pub async fn new() -> Music {
-        let theme_music = load_sound("8bit-spaceshooter.ogg").await.unwrap();
-        Music { theme_music }
+    pub fn new(assets: &Assets, list: MusicList) -> Music {
+        let sounds = list
+            .files()
+            .into_iter()
+            .map(|file| (file.to_string(), assets.sound(file)))
+            .collect();
+        Music {
+            list,
+            sounds,
+            state: None,
+            turns: HashMap::new(),
+            threat: 0.0,
+            paused: false,
+            volumes: vec![],
+            restart: false,
+        }
+    }
+
+    // fichier utilisé par l'une des listes (rechargement à chaud)
+    pub fn uses(&self, file: &str) -> bool {
+        self.sounds.contains_key(file)
+    }
+
+    // le changement d'état se fait en fondu enchaîné : un morceau qui joue déjà
+    // continue sans repartir du début, les autres démarrent avec le fondu (voir update) ;
+    // en entrant dans la partie, toutes les couches sont relancées pour rester calées
+    pub fn set_state(&mut self, state: MusicState) {
+        if self.state == Some(state) {
+            return;
+        }
+        if state == MusicState::Gameplay {
+            self.restart = true;
+        }
+        if let Some(key) = Music::playlist_key(state) {
+            *self.turns.entry(key.to_string()).or_insert(0) += 1;
+        }
+        self.state = Some(state);
+    }
+
+    pub fn set_threat(&mut self, threat: f32) {
+        self.threat = threat.clamp(0.0, 1.0);
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    fn playlist_key(state: MusicState) -> Option<&'static str> {
+        match state {
+            MusicState::Menu => Some("menu"),
+            MusicState::Boss => Some("boss"),
+            MusicState::GameOver => Some("game_over"),
+            MusicState::Victory => Some("victory"),
+            MusicState::Gameplay => None,
+        }
+    }
+
+    // volume visé pour chaque fichier dans l'état courant ; les couches muettes
+    // de la partie y sont aussi, pour continuer à tourner en mesure
+    fn targets(&self) -> Vec<(String, f32)> {
+        let level = if self.paused { PAUSED_LEVEL } else { 1.0 };
+        let playlist = |list: &[String], key: &str| match list.len() {
+            0 => vec![],
+            len => {
+                let turn = self.turns.get(key).copied().unwrap_or(1);
+                vec![(list[(turn - 1) % len].clone(), level)]
+            }
+        };
+        let mut targets = match self.state {
+            None => vec![],
+            Some(MusicState::Menu) => playlist(&self.list.menu, "menu"),
+            Some(MusicState::Gameplay) => self
+                .list
+                .gameplay
+                .iter()
+                .map(|stem| {
+                    let on = self.threat >= stem.threat;
+                    (stem.file.clone(), if on { level } else { 0.0 })
+                })
+                .collect(),
+            Some(MusicState::Boss) => playlist(&self.list.boss, "boss"),
+            Some(MusicState::GameOver) => playlist(&self.list.game_over, "game_over"),
+            Some(MusicState::Victory) => playlist(&self.list.victory, "victory"),
+        };
+        // un fichier cité deux fois prend le plus fort des deux volumes
+        targets.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+        targets.dedup_by(|a, b| a.0 == b.0);
+        targets
+    }
+
+    // avance les fondus ; à appeler une fois par frame
+    pub fn update(&mut self, delta_time: f32, mixer: &mut Mixer) {
+        let fade = if self.list.crossfade > 0.0 {
+            delta_time / self.list.crossfade
+        } else {
+            1.0
+        };
+        if self.restart {
+            for stem in &self.list.gameplay {
+                mixer.stop_music(&stem.file);
+                self.volumes.retain(|(file, _)| *file != stem.file);
+            }
+            self.restart = false;
+        }
+        let targets = self.targets();
+        for (file, _) in &targets {
+            if !self.volumes.iter().any(|(playing, _)| playing == file) {
+                mixer.play_music(file, &self.sounds[file], 0.0);
+                self.volumes.push((file.clone(), 0.0));
+            }
+        }
+        for (file, volume) in self.volumes.iter_mut() {
+            let target = targets
+                .iter()
+                .find(|(wanted, _)| wanted == file)
+                .map_or(0.0, |(_, target)| *target);
+            *volume += (target - *volume).clamp(-fade, fade);
+            mixer.set_music_volume(file, *volume);
+        }
+        // les morceaux qui ne sont plus voulus s'arrêtent à la fin de leur fondu
+        self.volumes.retain(|(file, volume)| {
+            let wanted = targets.iter().any(|(wanted, _)| wanted == file);
+            if !wanted && *volume <= 0.0 {
+                mixer.stop_music(file);
+            }
+            wanted || *volume > 0.0
+        });
     }
 
-    pub fn reset(&mut self) {
-        self.stop();
-        self.play();
+    // arrêt immédiat de tous les morceaux (musiques rechargées)
+    pub fn stop(&mut self, mixer: &mut Mixer) {
+        for (file, _) in self.volumes.drain(..) {
+            mixer.stop_music(&file);
+        }
+        self.state = None;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MUSIC: &str = r#"(
+        crossfade: 1.0,
+        menu: ["menu1.ogg", "menu2.ogg"],
+        gameplay: [
+            (file: "base.ogg", threat: 0.0),
+            (file: "drums.wav", threat: 0.25),
+            (file: "lead.wav", threat: 0.55),
+        ],
+        boss: ["boss.wav"],
+        game_over: [],
+        victory: ["base.ogg"],
+    )"#;
 
-    pub fn stop(&mut self) {
-        stop_sound(&self.theme_music);
+    // sans ressources chargées : seuls les volumes visés nous intéressent
+    fn music() -> Music {
+        Music {
+            list: MusicList::parse("test.ron", TEST_MUSIC).unwrap(),
+            sounds: HashMap::new(),
+            state: None,
+            turns: HashMap::new(),
+            threat: 0.0,
+            paused: false,
+            volumes: vec![],
+            restart: false,
+        }
     }
 
-    pub fn play(&mut self) {
-        play_sound(
-            &self.theme_music,
-            PlaySoundParams {
-                looped: true,
-                volume: 1.,
-            },
+    fn targets(music: &Music) -> Vec<(&'static str, f32)> {
+        let names = [
+            "base.ogg",
+            "boss.wav",
+            "drums.wav",
+            "lead.wav",
+            "menu1.ogg",
+            "menu2.ogg",
+        ];
+        music
+            .targets()
+            .into_iter()
+            .map(|(file, volume)| (*names.iter().find(|name| **name == file).unwrap(), volume))
+            .collect()
+    }
+
+    #[test]
+    fn built_in_music_keeps_the_theme_as_base_layer() {
+        let list = MusicList::default_music();
+        assert_eq!(list.gameplay[0].file, "8bit-spaceshooter.ogg");
+        assert_eq!(list.gameplay[0].threat, 0.0);
+        assert!(list.gameplay.iter().any(|stem| stem.threat > 0.0));
+    }
+
+    #[test]
+    fn threat_turns_layers_on() {
+        let mut music = music();
+        music.set_state(MusicState::Gameplay);
+        // les couches muettes sont dans la liste, pour tourner en mesure
+        assert_eq!(
+            targets(&music),
+            [("base.ogg", 1.0), ("drums.wav", 0.0), ("lead.wav", 0.0)]
+        );
+        music.set_threat(0.3);
+        assert_eq!(
+            targets(&music),
+            [("base.ogg", 1.0), ("drums.wav", 1.0), ("lead.wav", 0.0)]
+        );
+        music.set_threat(2.0);
+        assert_eq!(
+            targets(&music),
+            [("base.ogg", 1.0), ("drums.wav", 1.0), ("lead.wav", 1.0)]
         );
+        music.set_paused(true);
+        assert_eq!(
+            targets(&music),
+            [
+                ("base.ogg", PAUSED_LEVEL),
+                ("drums.wav", PAUSED_LEVEL),
+                ("lead.wav", PAUSED_LEVEL)
+            ]
+        );
+    }
+
+    #[test]
+    fn each_state_fades_to_its_own_tracks() {
+        let mut music = music();
+        assert!(targets(&music).is_empty());
+        music.set_state(MusicState::Menu);
+        assert_eq!(targets(&music), [("menu1.ogg", 1.0)]);
+        music.set_state(MusicState::Boss);
+        assert_eq!(targets(&music), [("boss.wav", 1.0)]);
+        music.set_state(MusicState::GameOver);
+        assert!(targets(&music).is_empty());
+        music.set_state(MusicState::Victory);
+        assert_eq!(targets(&music), [("base.ogg", 1.0)]);
+        // au retour dans le menu, morceau suivant de la liste, puis on recommence
+        music.set_state(MusicState::Menu);
+        assert_eq!(targets(&music), [("menu2.ogg", 1.0)]);
+        music.set_state(MusicState::Victory);
+        music.set_state(MusicState::Menu);
+        assert_eq!(targets(&music), [("menu1.ogg", 1.0)]);
+    }
+
+    #[test]
+    fn entering_gameplay_restarts_the_layers() {
+        let mut music = music();
+        music.set_state(MusicState::Menu);
+        assert!(!music.restart);
+        music.set_state(MusicState::Gameplay);
+        assert!(music.restart);
+        music.restart = false;
+        // rester dans la partie ne relance rien
+        music.set_state(MusicState::Gameplay);
+        assert!(!music.restart);
+        music.set_state(MusicState::GameOver);
+        music.set_state(MusicState::Gameplay);
+        assert!(music.restart);
     }
 }
@@ -0,0 +1,203 @@
+/* Structure OptionsScreen (réglages du joueur, dans une fenêtre habillée comme le menu) */
+
+use crate::input::*;
+use crate::settings::*;
+use macroquad::prelude::*;
+use macroquad::ui::{hash, root_ui, widgets};
+
+// pas des volumes au clavier et à la croix de la manette
+const VOLUME_STEP: f32 = 0.1;
+
+// choix qui fait quitter l'écran
+#[derive(Clone, Copy)]
+pub enum OptionsChoice {
+    Controls,
+    Back,
+}
+
+// lignes de l'écran, dans l'ordre d'affichage
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    UiVolume,
+    Mute,
+    Fullscreen,
+    ScreenShake,
+    Difficulty,
+    Controls,
+    Back,
+}
+
+const ROWS: [Row; 10] = [
+    Row::MasterVolume,
+    Row::MusicVolume,
+    Row::SfxVolume,
+    Row::UiVolume,
+    Row::Mute,
+    Row::Fullscreen,
+    Row::ScreenShake,
+    Row::Difficulty,
+    Row::Controls,
+    Row::Back,
+];
+
+pub struct OptionsScreen {
+    pub window_size: Vec2,
+    selected: usize,
+    // réglages modifiés, sauvegardés une fois le curseur d'un volume relâché
+    unsaved: bool,
+}
+
+impl OptionsScreen {
+    pub fn new() -> OptionsScreen {
+        OptionsScreen {
+            window_size: vec2(520.0, 700.0),
+            selected: 0,
+            unsaved: false,
+        }
+    }
+
+    // les réglages sont modifiés en direct, à la souris ou au clavier et à la manette :
+    // haut/bas pour choisir une ligne, gauche/droite pour changer sa valeur
+    pub fn display(
+        &mut self,
+        settings: &mut Settings,
+        backend: &dyn InputBackend,
+    ) -> Option<OptionsChoice> {
+        let map = &settings.controls;
+        let up = map.is_pressed(Action::MoveUp, backend);
+        let down = map.is_pressed(Action::MoveDown, backend);
+        let left = map.is_pressed(Action::MoveLeft, backend);
+        let right = map.is_pressed(Action::MoveRight, backend);
+        let confirm = map.is_pressed(Action::Confirm, backend);
+        let back = map.is_pressed(Action::Back, backend);
+        let mut choice = None;
+        if up {
+            self.selected = (self.selected + ROWS.len() - 1) % ROWS.len();
+        }
+        if down {
+            self.selected = (self.selected + 1) % ROWS.len();
+        }
+        let before = snapshot(settings);
+        let step = match (left, right) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        let mut shake = settings.screen_shake > 0.0;
+        let mut difficulty = settings.difficulty.index();
+        let volume_step = |volume: &mut f32| *volume += step as f32 * VOLUME_STEP;
+        match ROWS[self.selected] {
+            Row::MasterVolume => volume_step(&mut settings.master_volume),
+            Row::MusicVolume => volume_step(&mut settings.music_volume),
+            Row::SfxVolume => volume_step(&mut settings.sfx_volume),
+            Row::UiVolume => volume_step(&mut settings.ui_volume),
+            Row::Mute if step != 0 || confirm => settings.muted = !settings.muted,
+            Row::Fullscreen if step != 0 || confirm => settings.fullscreen = !settings.fullscreen,
+            Row::ScreenShake if step != 0 || confirm => shake = !shake,
+            Row::Difficulty => {
+                let step = if confirm { 1 } else { step };
+                let count = Difficulty::ALL.len() as i32;
+                difficulty = (difficulty as i32 + step).rem_euclid(count) as usize;
+            }
+            Row::Controls if confirm => choice = Some(OptionsChoice::Controls),
+            Row::Back if confirm => choice = Some(OptionsChoice::Back),
+            _ => {}
+        }
+        if back {
+            choice = Some(OptionsChoice::Back);
+        }
+        // la ligne choisie au clavier est marquée comme dans le menu principal
+        let selected = ROWS[self.selected];
+        let label = |row: Row, text: &str| {
+            if row == selected {
+                format!("> {}", text)
+            } else {
+                text.to_string()
+            }
+        };
+        let names = Difficulty::ALL.map(|difficulty| difficulty.name());
+        root_ui().window(
+            hash!(),
+            vec2(
+                screen_width() / 2.0 - self.window_size.x / 2.0,
+                screen_height() / 2.0 - self.window_size.y / 2.0,
+            ),
+            self.window_size,
+            |ui| {
+                ui.label(vec2(120.0, -34.0), "Options");
+                widgets::Slider::new(hash!(), 0.0..1.0)
+                    .label(&label(Row::MasterVolume, "Volume"))
+                    .ui(ui, &mut settings.master_volume);
+                widgets::Slider::new(hash!(), 0.0..1.0)
+                    .label(&label(Row::MusicVolume, "Music"))
+                    .ui(ui, &mut settings.music_volume);
+                widgets::Slider::new(hash!(), 0.0..1.0)
+                    .label(&label(Row::SfxVolume, "Effects"))
+                    .ui(ui, &mut settings.sfx_volume);
+                widgets::Slider::new(hash!(), 0.0..1.0)
+                    .label(&label(Row::UiVolume, "Interface"))
+                    .ui(ui, &mut settings.ui_volume);
+                widgets::Checkbox::new(hash!())
+                    .label(&label(Row::Mute, "Mute (F8)"))
+                    .ui(ui, &mut settings.muted);
+                widgets::Checkbox::new(hash!())
+                    .label(&label(Row::Fullscreen, "Fullscreen"))
+                    .ui(ui, &mut settings.fullscreen);
+                widgets::Checkbox::new(hash!())
+                    .label(&label(Row::ScreenShake, "Screen shake"))
+                    .ui(ui, &mut shake);
+                widgets::ComboBox::new(hash!(), &names)
+                    .label(&label(Row::Difficulty, "Difficulty"))
+                    .ui(ui, &mut difficulty);
+                if widgets::Button::new(label(Row::Controls, "Controls").as_str()).ui(ui) {
+                    choice = Some(OptionsChoice::Controls);
+                }
+                if widgets::Button::new(label(Row::Back, "Back").as_str()).ui(ui) {
+                    choice = Some(OptionsChoice::Back);
+                }
+            },
+        );
+        // la boîte à cocher garde l'intensité choisie si elle n'est pas décochée
+        if shake != (settings.screen_shake > 0.0) {
+            settings.screen_shake = if shake { 1.0 } else { 0.0 };
+        }
+        settings.difficulty = Difficulty::ALL[difficulty];
+        for volume in [
+            &mut settings.master_volume,
+            &mut settings.music_volume,
+            &mut settings.sfx_volume,
+            &mut settings.ui_volume,
+        ] {
+            *volume = volume.clamp(0.0, 1.0);
+        }
+        if settings.fullscreen != before.1[1] {
+            set_fullscreen(settings.fullscreen);
+        }
+        if snapshot(settings) != before {
+            self.unsaved = true;
+        }
+        if self.unsaved && (choice.is_some() || !is_mouse_button_down(MouseButton::Left)) {
+            settings.save();
+            self.unsaved = false;
+        }
+        choice
+    }
+}
+
+// réglages modifiables sur cet écran (les touches sont sauvegardées par ControlsScreen)
+fn snapshot(settings: &Settings) -> ([f32; 5], [bool; 2], Difficulty) {
+    (
+        [
+            settings.master_volume,
+            settings.music_volume,
+            settings.sfx_volume,
+            settings.ui_volume,
+            settings.screen_shake,
+        ],
+        [settings.muted, settings.fullscreen],
+        settings.difficulty,
+    )
+}
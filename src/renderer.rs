@@ -0,0 +1,53 @@
+/* Structure Renderer (affichage du monde) */
+
+use crate::archetypes::Archetypes;
+use crate::assets::Assets;
+use crate::buildings::*;
+use crate::bullets::*;
+use crate::enemies::*;
+use crate::level::Level;
+use crate::ship::*;
+use crate::world::World;
+use macroquad::prelude::*;
+
+pub struct Renderer {
+    pub ship: ShipView,
+    pub enemies: EnemiesView,
+    pub bullets: BulletsView,
+    pub buildings: BuildingsView,
+}
+
+impl Renderer {
+    pub fn new(level: &Level, archetypes: &Archetypes, assets: &Assets) -> Renderer {
+        Renderer {
+            ship: ShipView::new(assets),
+            enemies: EnemiesView::new(archetypes, assets),
+            bullets: BulletsView::new(assets),
+            buildings: BuildingsView::new(level, assets),
+        }
+    }
+
+    // mise à jour des animations
+    pub fn update(&mut self, world: &World) {
+        self.ship.update(&world.ship);
+        self.enemies.update(&world.enemies);
+        self.bullets.update();
+        self.buildings.update(&world.buildings);
+    }
+
+    // affichage du monde, interpolé entre les deux derniers pas de simulation
+    pub fn display(&self, world: &World, alpha: f32) {
+        self.ship.display(&world.ship, alpha);
+        self.enemies.display(&world.enemies, alpha);
+        self.bullets.display(&world.bullets, alpha);
+        world.score.display();
+        self.buildings.display(&world.buildings);
+    }
+
+    // viseur au point visé à la souris
+    pub fn display_crosshair(&self, aim: Vec2) {
+        draw_circle_lines(aim.x, aim.y, 10.0, 2.0, RED);
+        draw_line(aim.x - 15.0, aim.y, aim.x + 15.0, aim.y, 1.0, RED);
+        draw_line(aim.x, aim.y - 15.0, aim.x, aim.y + 15.0, 1.0, RED);
+    }
+}
@@ -0,0 +1,579 @@
+/* Structure Replay (enregistrement et relecture des parties) */
+
+use crate::events::GameEvent;
+use crate::input::{Action, InputBackend, InputMap};
+use crate::settings::Difficulty;
+use crate::timestep::FixedTimestep;
+use crate::world::{InputState, World};
+use macroquad::prelude::*;
+use std::fmt;
+
+// format du fichier : "BGRP", version, graine, difficulté, taille de la zone de jeu, durée d'un pas,
+// scène de test, fichier de la ville (longueur sur un octet puis le nom) et nombre de pas,
+// puis pour chaque pas de simulation : boutons et direction (x, y sur un octet signé),
+// suivis du point visé (x, y sur deux octets signés) seulement en visée à la souris
+const MAGIC: &[u8; 4] = b"BGRP";
+const VERSION: u8 = 1;
+// en-tête sans le nom de la ville
+const HEADER_SIZE: usize = 4 + 1 + 8 + 1 + 4 + 4 + 4 + 4 + 1 + 4;
+const FRAME_SIZE: usize = 3;
+const AIM_SIZE: usize = 4;
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_FILE: &str = "last.replay";
+#[cfg(target_arch = "wasm32")]
+const REPLAY_KEY: &str = "replay";
+const SEEK_FRAMES: usize = 120;
+const SPEEDS: [usize; 3] = [1, 2, 4];
+
+const INPUT_SHOOT: u8 = 1;
+const INPUT_PAUSE: u8 = 1 << 1;
+const INPUT_AIM: u8 = 1 << 2;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(String),
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownDifficulty(u8),
+    BadLevelName,
+    Truncated,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(message) => write!(f, "replay: {}", message),
+            ReplayError::BadMagic => write!(f, "replay: not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "replay: unsupported version {}", version)
+            }
+            ReplayError::UnknownDifficulty(difficulty) => {
+                write!(f, "replay: unknown difficulty {}", difficulty)
+            }
+            ReplayError::BadLevelName => write!(f, "replay: invalid level file name"),
+            ReplayError::Truncated => write!(f, "replay: file is truncated"),
+        }
+    }
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub width: f32,
+    pub height: f32,
+    pub step: f32,
+    // nombre d'ennemis et de balles de la scène de test (0 = partie normale)
+    pub stress: usize,
+    pub level: String,
+    pub frames: Vec<InputState>,
+}
+
+impl Replay {
+    // partie qui commence : tout ce qui change la simulation est noté dans l'en-tête
+    pub fn new(world: &World, difficulty: Difficulty, level: &str, step: f32) -> Replay {
+        Replay {
+            seed: world.seed,
+            difficulty,
+            width: world.width,
+            height: world.height,
+            step,
+            stress: world.stress,
+            level: level.to_string(),
+            frames: vec![],
+        }
+    }
+
+    // enregistrement des commandes d'un pas de simulation
+    pub fn push(&mut self, input: &InputState) {
+        self.frames.push(*input);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // le nom tient sur 255 octets, coupé entre deux caractères
+        let mut len = self.level.len().min(u8::MAX as usize);
+        while !self.level.is_char_boundary(len) {
+            len -= 1;
+        }
+        let level = &self.level.as_bytes()[..len];
+        let mut bytes =
+            Vec::with_capacity(HEADER_SIZE + level.len() + self.frames.len() * FRAME_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.difficulty.index() as u8);
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&self.step.to_le_bytes());
+        bytes.extend_from_slice(&(self.stress as u32).to_le_bytes());
+        bytes.push(level.len() as u8);
+        bytes.extend_from_slice(level);
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for input in &self.frames {
+            encode_input(input, &mut bytes);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, ReplayError> {
+        if bytes.len() < 5 || &bytes[0..4] != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(ReplayError::UnsupportedVersion(bytes[4]));
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(ReplayError::Truncated);
+        }
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let difficulty = *Difficulty::ALL
+            .get(bytes[13] as usize)
+            .ok_or(ReplayError::UnknownDifficulty(bytes[13]))?;
+        let width = f32::from_le_bytes(bytes[14..18].try_into().unwrap());
+        let height = f32::from_le_bytes(bytes[18..22].try_into().unwrap());
+        let step = f32::from_le_bytes(bytes[22..26].try_into().unwrap());
+        let stress = u32::from_le_bytes(bytes[26..30].try_into().unwrap()) as usize;
+        let level_end = 31 + bytes[30] as usize;
+        let level = bytes.get(31..level_end).ok_or(ReplayError::Truncated)?;
+        let level = String::from_utf8(level.to_vec()).map_err(|_| ReplayError::BadLevelName)?;
+        let count = bytes
+            .get(level_end..level_end + 4)
+            .ok_or(ReplayError::Truncated)?;
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        let mut data = &bytes[level_end + 4..];
+        // le nombre de pas vient du fichier : on ne réserve pas plus que ce qu'il peut contenir
+        let mut frames = Vec::with_capacity(count.min(data.len() / FRAME_SIZE));
+        for _ in 0..count {
+            let (input, size) = decode_input(data).ok_or(ReplayError::Truncated)?;
+            frames.push(input);
+            data = &data[size..];
+        }
+        Ok(Replay {
+            seed,
+            difficulty,
+            width,
+            height,
+            step,
+            stress,
+            level,
+            frames,
+        })
+    }
+
+    // sauvegarde de la dernière partie (fichier en natif, stockage local dans le navigateur)
+    pub fn save(&self) -> Result<(), ReplayError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            std::fs::write(REPLAY_FILE, self.to_bytes())
+                .map_err(|e| ReplayError::Io(format!("{}: {}", REPLAY_FILE, e)))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let storage = &mut quad_storage::STORAGE.lock().unwrap();
+            storage.set(REPLAY_KEY, &to_hex(&self.to_bytes()));
+            Ok(())
+        }
+    }
+
+    pub fn load() -> Result<Replay, ReplayError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let bytes = std::fs::read(REPLAY_FILE)
+                .map_err(|e| ReplayError::Io(format!("{}: {}", REPLAY_FILE, e)))?;
+            Replay::from_bytes(&bytes)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let storage = &mut quad_storage::STORAGE.lock().unwrap();
+            let hex = storage
+                .get(REPLAY_KEY)
+                .ok_or_else(|| ReplayError::Io("no replay saved".to_string()))?;
+            Replay::from_bytes(&from_hex(&hex).ok_or(ReplayError::BadMagic)?)
+        }
+    }
+}
+
+// les axes et le point visé sont déjà arrondis à la source (voir InputMap::read),
+// l'encodage est donc exact
+fn encode_input(input: &InputState, bytes: &mut Vec<u8>) {
+    let mut bits = 0;
+    if input.shoot {
+        bits |= INPUT_SHOOT;
+    }
+    if input.pause {
+        bits |= INPUT_PAUSE;
+    }
+    if input.aim.is_some() {
+        bits |= INPUT_AIM;
+    }
+    let axis = |value: f32| ((value.clamp(-1.0, 1.0) * 127.0).round() as i8) as u8;
+    bytes.extend_from_slice(&[bits, axis(input.movement.x), axis(input.movement.y)]);
+    if let Some(aim) = input.aim {
+        bytes.extend_from_slice(&(aim.x as i16).to_le_bytes());
+        bytes.extend_from_slice(&(aim.y as i16).to_le_bytes());
+    }
+}
+
+// retourne les commandes et le nombre d'octets lus
+fn decode_input(bytes: &[u8]) -> Option<(InputState, usize)> {
+    let frame = bytes.get(..FRAME_SIZE)?;
+    let axis = |byte: u8| (byte as i8) as f32 / 127.0;
+    let mut size = FRAME_SIZE;
+    let aim = if frame[0] & INPUT_AIM != 0 {
+        let aim = bytes.get(FRAME_SIZE..FRAME_SIZE + AIM_SIZE)?;
+        size += AIM_SIZE;
+        Some(vec2(
+            i16::from_le_bytes([aim[0], aim[1]]) as f32,
+            i16::from_le_bytes([aim[2], aim[3]]) as f32,
+        ))
+    } else {
+        None
+    };
+    let input = InputState {
+        movement: vec2(axis(frame[1]), axis(frame[2])),
+        shoot: frame[0] & INPUT_SHOOT != 0,
+        pause: frame[0] & INPUT_PAUSE != 0,
+        aim,
+    };
+    Some((input, size))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// relecture d'une partie : pause, vitesse x1/x2/x4 et déplacement dans le temps
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    pub frame: usize,
+    pub speed: usize,
+    pub paused: bool,
+    timestep: FixedTimestep,
+    // direction tenue à la frame précédente : un stick poussé ne compte qu'une fois
+    held: Vec2,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay, world: &mut World) -> ReplayPlayer {
+        let mut player = ReplayPlayer {
+            replay,
+            frame: 0,
+            speed: 1,
+            paused: false,
+            timestep: FixedTimestep::new(),
+            held: Vec2::ZERO,
+        };
+        player.seek(world, 0);
+        player
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+
+    // déplacement jusqu'à la frame demandée, sans effets : vers l'avant on continue
+    // depuis la frame en cours, vers l'arrière on rejoue la partie depuis le début
+    pub fn seek(&mut self, world: &mut World, frame: usize) {
+        let target = frame.min(self.replay.frames.len());
+        if target < self.frame || target == 0 {
+            world.set_bounds(self.replay.width, self.replay.height);
+            world.stress = self.replay.stress;
+            world.reset(self.replay.seed, self.replay.difficulty);
+            self.frame = 0;
+        }
+        self.timestep.reset();
+        while self.frame < target {
+            self.step_frame(world);
+        }
+    }
+
+    fn step_frame(&mut self, world: &mut World) -> Vec<GameEvent> {
+        let input = self.replay.frames[self.frame];
+        self.frame += 1;
+        world.step(self.replay.step, &input)
+    }
+
+    // commandes du lecteur : gauche/droite pour se déplacer, haut/bas pour la vitesse,
+    // lues comme la direction du vaisseau (touches, croix, stick ou stick tactile)
+    pub fn handle_keys(
+        &mut self,
+        world: &mut World,
+        input_map: &InputMap,
+        backend: &dyn InputBackend,
+    ) {
+        if input_map.is_pressed(Action::Confirm, backend) || backend.touch().pause {
+            self.paused = !self.paused;
+        }
+        let direction = input_map.read(backend, false).movement.round();
+        let pushed = |now: f32, before: f32| if now != before { now } else { 0.0 };
+        let speed = SPEEDS.iter().position(|s| *s == self.speed).unwrap_or(0);
+        let vertical = pushed(direction.y, self.held.y);
+        if vertical < 0.0 {
+            self.speed = SPEEDS[(speed + 1).min(SPEEDS.len() - 1)];
+        } else if vertical > 0.0 {
+            self.speed = SPEEDS[speed.saturating_sub(1)];
+        }
+        let horizontal = pushed(direction.x, self.held.x);
+        if horizontal < 0.0 {
+            self.seek(world, self.frame.saturating_sub(SEEK_FRAMES));
+        } else if horizontal > 0.0 {
+            self.seek(world, self.frame + SEEK_FRAMES);
+        }
+        self.held = direction;
+        if input_map.is_pressed(Action::Restart, backend) {
+            self.seek(world, 0);
+        }
+    }
+
+    // avance la relecture du temps d'une frame d'affichage (plus de pas en accéléré)
+    pub fn update(&mut self, world: &mut World, frame_time: f32) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if self.paused {
+            return events;
+        }
+        for _ in 0..self.timestep.advance(frame_time * self.speed as f32) {
+            if self.finished() {
+                break;
+            }
+            events.extend(self.step_frame(world));
+        }
+        events
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.timestep.steps
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.timestep.alpha()
+    }
+
+    pub fn display(&self, input_map: &InputMap) {
+        let status = format!(
+            "REPLAY  frame {}/{}  x{}{}",
+            self.frame,
+            self.replay.frames.len(),
+            self.speed,
+            if self.paused { "  (paused)" } else { "" }
+        );
+        draw_text(&status, 10.0, screen_height() - 30.0, 20.0, YELLOW);
+        draw_text(
+            &format!(
+                "{}: pause  Up/Down: speed  Left/Right: seek  {}: restart  {}: menu",
+                input_map.key_name(Action::Confirm),
+                input_map.key_name(Action::Restart),
+                input_map.key_name(Action::Back)
+            ),
+            10.0,
+            screen_height() - 10.0,
+            16.0,
+            WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetypes::Archetypes;
+    use crate::gamepad::GamepadButton;
+    use crate::input::tests::FakeBackend;
+    use crate::level::Level;
+    use crate::masks::MaskLibrary;
+    use crate::score::Score;
+    use crate::waves::WaveList;
+
+    fn sample() -> Replay {
+        let frames = (0..50)
+            .map(|i| InputState {
+                movement: vec2((i % 3) as f32 - 1.0, -1.0),
+                shoot: i % 4 == 0,
+                pause: i == 10,
+                aim: (i % 5 == 0).then(|| vec2(i as f32 * 3.0, -20.0)),
+            })
+            .collect();
+        Replay {
+            seed: 987654321,
+            difficulty: Difficulty::Hard,
+            width: 1024.0,
+            height: 768.0,
+            step: 1.0 / 120.0,
+            stress: 300,
+            level: "desert.ron".to_string(),
+            frames,
+        }
+    }
+
+    #[test]
+    fn replay_survives_a_round_trip() {
+        let replay = sample();
+        let read = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(read.seed, replay.seed);
+        assert_eq!(read.difficulty, replay.difficulty);
+        assert_eq!((read.width, read.height), (replay.width, replay.height));
+        assert_eq!(read.step, replay.step);
+        assert_eq!(read.stress, replay.stress);
+        assert_eq!(read.level, replay.level);
+        assert_eq!(read.frames.len(), replay.frames.len());
+        for (a, b) in read.frames.iter().zip(&replay.frames) {
+            assert_eq!(a.movement, b.movement);
+            assert_eq!((a.shoot, a.pause, a.aim), (b.shoot, b.pause, b.aim));
+        }
+    }
+
+    #[test]
+    fn long_level_name_is_cut_between_characters() {
+        let mut replay = sample();
+        replay.level = "é".repeat(200);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.level, "é".repeat(127));
+    }
+
+    #[test]
+    fn truncated_replay_is_rejected() {
+        let bytes = sample().to_bytes();
+        for length in [HEADER_SIZE - 1, HEADER_SIZE + 5, bytes.len() - 1] {
+            assert!(matches!(
+                Replay::from_bytes(&bytes[..length]),
+                Err(ReplayError::Truncated)
+            ));
+        }
+    }
+
+    #[test]
+    fn huge_frame_count_does_not_allocate() {
+        let mut bytes = sample().to_bytes();
+        let count_at = 31 + "desert.ron".len();
+        bytes[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn other_files_are_not_replays() {
+        assert!(matches!(
+            Replay::from_bytes(b"RIFF1234"),
+            Err(ReplayError::BadMagic)
+        ));
+        let mut bytes = sample().to_bytes();
+        bytes[4] = VERSION + 1;
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::UnsupportedVersion(_))
+        ));
+    }
+
+    fn world() -> World {
+        let archetypes = Archetypes::default_archetypes();
+        let waves = WaveList::default_waves(&archetypes);
+        World::new(
+            800.0,
+            800.0,
+            0,
+            Level::default_city(),
+            archetypes,
+            waves,
+            MaskLibrary::new(),
+            Score::new(0),
+        )
+    }
+
+    fn long_replay() -> Replay {
+        let mut replay = sample();
+        replay.stress = 0;
+        replay.frames = (0..2400)
+            .map(|i| InputState {
+                movement: vec2(if i % 240 < 120 { 1.0 } else { -1.0 }, 0.0),
+                shoot: i % 30 == 0,
+                aim: None,
+                pause: false,
+            })
+            .collect();
+        replay
+    }
+
+    fn state(world: &World) -> (Vec<(f32, f32)>, u32, f32) {
+        let enemies = world.enemies.enemies.iter().map(|e| (e.x, e.y)).collect();
+        (enemies, world.score.value(), world.ship.ship.x)
+    }
+
+    #[test]
+    fn seeking_gives_the_same_world_from_any_frame() {
+        let mut direct_world = world();
+        let mut direct = ReplayPlayer::new(long_replay(), &mut direct_world);
+        direct.seek(&mut direct_world, 1500);
+
+        // vers l'avant par petits bonds, puis trop loin et retour en arrière
+        let mut world = world();
+        let mut player = ReplayPlayer::new(long_replay(), &mut world);
+        for frame in (0..=1500).step_by(SEEK_FRAMES) {
+            player.seek(&mut world, frame);
+        }
+        player.seek(&mut world, 1500);
+        assert_eq!(player.frame, 1500);
+        assert!(!world.enemies.enemies.is_empty());
+        assert_eq!(state(&world), state(&direct_world));
+        player.seek(&mut world, 2000);
+        player.seek(&mut world, 1500);
+        assert_eq!(state(&world), state(&direct_world));
+        // au-delà de la fin, on s'arrête sur la dernière frame
+        player.seek(&mut world, 10_000);
+        assert!(player.finished());
+    }
+
+    #[test]
+    fn replay_controls_work_from_keys_gamepad_and_touch() {
+        let map = InputMap::new();
+        let mut backend = FakeBackend::default();
+        let mut world = world();
+        let mut player = ReplayPlayer::new(long_replay(), &mut world);
+
+        // une touche tenue ne déplace qu'une fois
+        backend.keys = vec![KeyCode::Right];
+        player.handle_keys(&mut world, &map, &backend);
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.frame, SEEK_FRAMES);
+        backend.keys.clear();
+        player.handle_keys(&mut world, &map, &backend);
+
+        backend.hold(&[GamepadButton::DpadUp]);
+        player.handle_keys(&mut world, &map, &backend);
+        backend.hold(&[]);
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.speed, 2);
+        backend.gamepad.set_stick(vec2(1.0, 0.0));
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.frame, 2 * SEEK_FRAMES);
+        backend.gamepad.set_stick(Vec2::ZERO);
+        backend.hold(&[GamepadButton::South]);
+        player.handle_keys(&mut world, &map, &backend);
+        assert!(player.paused);
+        backend.hold(&[]);
+
+        backend.touch.stick = vec2(-1.0, 0.0);
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.frame, SEEK_FRAMES);
+        backend.touch.stick = vec2(0.0, 1.0);
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.speed, 1);
+        backend.touch.stick = Vec2::ZERO;
+        backend.touch.pause = true;
+        player.handle_keys(&mut world, &map, &backend);
+        assert!(!player.paused);
+        backend.touch.pause = false;
+
+        backend.hold(&[GamepadButton::Select]);
+        player.handle_keys(&mut world, &map, &backend);
+        assert_eq!(player.frame, 0);
+    }
+}
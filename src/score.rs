@@ -1,3 +1,4 @@
+use crate::events::*;
 use macroquad::prelude::*;
 
 pub struct Score {
@@ -6,17 +7,24 @@ pub struct Score {
 }
 
 impl Score {
-    pub fn new() -> Score {
-        let score: u32 = 0;
+    pub fn new(high_score: u32) -> Score {
+        Score {
+            score: 0,
+            high_score,
+        }
+    }
+
+    // score à zéro avec le meilleur score enregistré
+    pub fn load() -> Score {
         let storage = &mut quad_storage::STORAGE.lock().unwrap(); // Récupération du mutex
         let high_score: u32 = storage
             .get("highscore")
             .and_then(|s| s.parse::<u32>().ok())
             .unwrap_or(0);
-        Score { score, high_score }
+        Score::new(high_score)
     }
 
-    pub fn display(&mut self) {
+    pub fn display(&self) {
         draw_text(
             format!("Score: {}", self.score).as_str(),
             10.0,
@@ -40,11 +48,15 @@ impl Score {
         self.high_score = self.high_score.max(self.score);
     }
 
+    pub fn value(&self) -> u32 {
+        self.score
+    }
+
     pub fn reset(&mut self) {
         self.score = 0;
     }
 
-    pub fn display_high_score(&mut self, font: &Font) {
+    pub fn display_high_score(&mut self, font: Option<&Font>) {
         if self.score == self.high_score {
             let s = self.high_score.to_string();
             let storage = &mut quad_storage::STORAGE.lock().unwrap();
@@ -52,7 +64,7 @@ impl Score {
             let text = "CONGRATULATIONS ! you reached a high score";
             let text_params = TextParams {
                 font_size: 25,
-                font: Some(font),
+                font,
                 color: YELLOW,
                 font_scale: 1.0,
                 ..Default::default()
@@ -72,3 +84,12 @@ impl Score {
         }
     }
 }
+
+// les points des ennemis détruits
+impl Subscriber for Score {
+    fn notify(&mut self, event: &GameEvent) {
+        if let GameEvent::EnemyDestroyed { points, .. } = event {
+            self.increase(*points);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+/* Structure Settings (réglages du joueur, sauvegardés avec quad_storage) */
+
+use crate::input::InputMap;
+use std::str::FromStr;
+
+// une clé par réglage : une valeur illisible ne fait perdre que ce réglage
+const MASTER_VOLUME_KEY: &str = "master_volume";
+const MUSIC_VOLUME_KEY: &str = "music_volume";
+const SFX_VOLUME_KEY: &str = "sfx_volume";
+const UI_VOLUME_KEY: &str = "ui_volume";
+const MUTED_KEY: &str = "muted";
+const FULLSCREEN_KEY: &str = "fullscreen";
+const SCREEN_SHAKE_KEY: &str = "screen_shake";
+const LANGUAGE_KEY: &str = "language";
+const DIFFICULTY_KEY: &str = "difficulty";
+const MOUSE_AIM_KEY: &str = "mouse_aim";
+const AUTO_FIRE_KEY: &str = "auto_fire";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    English,
+    French,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::French];
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    // rang dans ALL (stocké dans les replays)
+    pub fn index(&self) -> usize {
+        Difficulty::ALL.iter().position(|d| d == self).unwrap()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    // multiplicateur de la cadence d'apparition des ennemis
+    pub fn spawn_factor(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.3,
+        }
+    }
+
+    // multiplicateur de la vitesse des ennemis
+    pub fn speed_factor(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+}
+
+// les deux énumérations sont stockées sous leur nom de variante ("French", "Hard")
+impl FromStr for Language {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Language, ()> {
+        from_debug_name(&Language::ALL, s)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Difficulty, ()> {
+        from_debug_name(&Difficulty::ALL, s)
+    }
+}
+
+fn from_debug_name<T: Copy + std::fmt::Debug>(all: &[T], name: &str) -> Result<T, ()> {
+    all.iter()
+        .find(|value| format!("{:?}", value) == name)
+        .copied()
+        .ok_or(())
+}
+
+pub struct Settings {
+    // volumes entre 0 et 1 : général, puis un par bus du mixer
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub ui_volume: f32,
+    pub muted: bool,
+    pub fullscreen: bool,
+    // amplitude des secousses de l'écran entre 0 (aucune) et 1
+    pub screen_shake: f32,
+    pub language: Language,
+    pub difficulty: Difficulty,
+    // touches, sauvegardées sous leurs propres clés par InputMap
+    pub controls: InputMap,
+    // visée vers le curseur de la souris plutôt que droit devant
+    pub mouse_aim: bool,
+    // tir automatique tant que le bouton FIRE de l'écran tactile reste enfoncé
+    pub auto_fire: bool,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            master_volume: 1.0,
+            music_volume: 0.8,
+            sfx_volume: 1.0,
+            ui_volume: 0.8,
+            muted: false,
+            fullscreen: false,
+            screen_shake: 1.0,
+            language: Language::English,
+            difficulty: Difficulty::Normal,
+            controls: InputMap::new(),
+            mouse_aim: false,
+            auto_fire: false,
+        }
+    }
+
+    // lecture depuis le stockage (fichier en natif, stockage local dans le navigateur) ;
+    // une clé absente ou illisible garde sa valeur par défaut
+    pub fn load() -> Settings {
+        let defaults = Settings::new();
+        let controls = InputMap::load();
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        let read_volume = |key: &str, default: f32| {
+            storage
+                .get(key)
+                .and_then(|s| s.parse::<f32>().ok())
+                .filter(|volume| volume.is_finite())
+                .map_or(default, |volume| volume.clamp(0.0, 1.0))
+        };
+        let master_volume = read_volume(MASTER_VOLUME_KEY, defaults.master_volume);
+        let music_volume = read_volume(MUSIC_VOLUME_KEY, defaults.music_volume);
+        let sfx_volume = read_volume(SFX_VOLUME_KEY, defaults.sfx_volume);
+        let ui_volume = read_volume(UI_VOLUME_KEY, defaults.ui_volume);
+        let screen_shake = read_volume(SCREEN_SHAKE_KEY, defaults.screen_shake);
+        Settings {
+            master_volume,
+            music_volume,
+            sfx_volume,
+            ui_volume,
+            muted: read(storage, MUTED_KEY, defaults.muted),
+            fullscreen: read(storage, FULLSCREEN_KEY, defaults.fullscreen),
+            screen_shake,
+            language: read(storage, LANGUAGE_KEY, defaults.language),
+            difficulty: read(storage, DIFFICULTY_KEY, defaults.difficulty),
+            controls,
+            mouse_aim: read(storage, MOUSE_AIM_KEY, defaults.mouse_aim),
+            auto_fire: read(storage, AUTO_FIRE_KEY, defaults.auto_fire),
+        }
+    }
+
+    pub fn save(&self) {
+        self.controls.save();
+        let storage = &mut quad_storage::STORAGE.lock().unwrap();
+        storage.set(MASTER_VOLUME_KEY, &self.master_volume.to_string());
+        storage.set(MUSIC_VOLUME_KEY, &self.music_volume.to_string());
+        storage.set(SFX_VOLUME_KEY, &self.sfx_volume.to_string());
+        storage.set(UI_VOLUME_KEY, &self.ui_volume.to_string());
+        storage.set(MUTED_KEY, &self.muted.to_string());
+        storage.set(FULLSCREEN_KEY, &self.fullscreen.to_string());
+        storage.set(SCREEN_SHAKE_KEY, &self.screen_shake.to_string());
+        storage.set(LANGUAGE_KEY, &format!("{:?}", self.language));
+        storage.set(DIFFICULTY_KEY, &format!("{:?}", self.difficulty));
+        storage.set(MOUSE_AIM_KEY, &self.mouse_aim.to_string());
+        storage.set(AUTO_FIRE_KEY, &self.auto_fire.to_string());
+    }
+}
+
+fn read<T: FromStr>(storage: &quad_storage::LocalStorage, key: &str, default: T) -> T {
+    storage
+        .get(key)
+        .and_then(|s| s.parse::<T>().ok())
+        .unwrap_or(default)
+}
@@ -1,7 +1,10 @@
 use macroquad::prelude::*;
 
-const VERTEX_SHADER: &str = include_str!("vertex_shader.glsl");
-const FRAGMENT_SHADER: &str = include_str!("fragment_shader.glsl");
+const VERTEX_SHADER_FILE: &str = "vertex_shader.glsl";
+const FRAGMENT_SHADER_FILE: &str = "fragment_shader.glsl";
+// copies intégrées au jeu, utilisées au démarrage
+const VERTEX_SHADER: &str = include_str!("../assets/vertex_shader.glsl");
+const FRAGMENT_SHADER: &str = include_str!("../assets/fragment_shader.glsl");
 
 pub struct Shader {
     texture: Texture2D,
@@ -13,11 +16,14 @@ impl Shader {
         let img = Image::gen_image_color(1, 1, WHITE);
         let texture = Texture2D::from_image(&img);
 
-        let material = load_material(
-            ShaderSource::Glsl {
-                vertex: VERTEX_SHADER,
-                fragment: FRAGMENT_SHADER,
-            },
+        let material =
+            Shader::compile(VERTEX_SHADER, FRAGMENT_SHADER).expect("invalid built-in shader");
+        Shader { texture, material }
+    }
+
+    fn compile(vertex: &str, fragment: &str) -> Result<Material, String> {
+        load_material(
+            ShaderSource::Glsl { vertex, fragment },
             MaterialParams {
                 uniforms: vec![
                     UniformDesc::new("time", UniformType::Float1),
@@ -26,17 +32,29 @@ impl Shader {
                 ..Default::default()
             },
         )
-        .unwrap();
-        Shader {
-            texture,
-            material,
-        }
+        .map_err(|e| match e {
+            macroquad::Error::ShaderError(e) => e.to_string(),
+            e => format!("{:?}", e),
+        })
+    }
+
+    // relecture des sources dans assets (rechargement à chaud) ; en cas d'erreur on garde l'ancien shader
+    pub async fn reload(&mut self) -> Result<(), String> {
+        let vertex = load_string(VERTEX_SHADER_FILE)
+            .await
+            .map_err(|e| format!("{}: {}", VERTEX_SHADER_FILE, e))?;
+        let fragment = load_string(FRAGMENT_SHADER_FILE)
+            .await
+            .map_err(|e| format!("{}: {}", FRAGMENT_SHADER_FILE, e))?;
+        self.material = Shader::compile(&vertex, &fragment)?;
+        Ok(())
     }
 
     pub fn display(&mut self) {
         gl_use_material(&self.material);
         self.material.set_uniform("time", get_time() as f32);
-        self.material.set_uniform("screen_size", vec2(screen_width(), screen_height()));
+        self.material
+            .set_uniform("screen_size", vec2(screen_width(), screen_height()));
         draw_texture_ex(
             &self.texture,
             0.0,
@@ -1,7 +1,10 @@
 /* Structure ShipSet (gestion du vaisseau) */
 
 use crate::Shape;
-use macroquad::audio::{Sound, load_sound, play_sound_once};
+use crate::assets::Assets;
+use crate::atlas::AtlasSprite;
+use crate::collider::ColliderDef;
+use crate::world::InputState;
 use macroquad::experimental::animation::AnimatedSprite;
 use macroquad::experimental::animation::Animation;
 use macroquad::prelude::*;
@@ -9,39 +12,127 @@ use macroquad::prelude::*;
 const MOVEMENT_SPEED: f32 = 500.0;
 const NB_LIFE: i32 = 5;
 const NB_AMMO: i32 = 100;
+// silhouette du vaisseau, en pixels de l'image de ship.png (16x24, affichée en double)
+const SHIP_OUTLINE: [(f32, f32); 10] = [
+    (6.0, 0.0),
+    (10.0, 0.0),
+    (16.0, 6.0),
+    (16.0, 14.0),
+    (13.0, 19.0),
+    (9.0, 24.0),
+    (8.0, 24.0),
+    (3.0, 19.0),
+    (0.0, 14.0),
+    (0.0, 6.0),
+];
+// le trait de laser dans l'image de laser-bolts.png (16x16, affichée en double)
+const BOLT: ColliderDef = ColliderDef::Obb {
+    x: 7.5,
+    y: 8.0,
+    width: 5.0,
+    height: 12.0,
+    angle: 0.0,
+};
 
 pub struct Ship {
     pub ship: Shape,
+    pub nb_ammo: i32,
+    pub animation: usize,
+}
+
+// ressources d'affichage du vaisseau (textures et animation)
+pub struct ShipView {
     pub ship_sprite: AnimatedSprite,
-    pub sound_laser: Sound,
-    ship_texture: Texture2D,
-    heart_texture: Texture2D,
-    heart2_texture: Texture2D,
-    ammo_texture: Texture2D,
-    ammo2_texture: Texture2D,
-    pub nb_ammo:i32
+    ship_texture: AtlasSprite,
+    heart_texture: AtlasSprite,
+    heart2_texture: AtlasSprite,
+    ammo_texture: AtlasSprite,
+    ammo2_texture: AtlasSprite,
 }
 
 impl Ship {
-    pub async fn new() -> Ship {
+    pub fn new(width: f32, height: f32) -> Ship {
         let ship = Shape {
             size: 16.0,
             speed: MOVEMENT_SPEED,
-            x: screen_width() / 2.0,
-            y: screen_height() / 2.0,
+            x: width / 2.0,
+            y: height / 2.0,
             collided: false,
-            life: NB_LIFE
+            life: NB_LIFE,
+            prev_x: width / 2.0,
+            prev_y: height / 2.0,
+            velocity: Vec2::ZERO,
+            kind: 0,
+            collider: ColliderDef::Polygon {
+                points: SHIP_OUTLINE.to_vec(),
+            }
+            .to_collider(16.0, 24.0, 2.0, None),
         };
-        let ship_texture: Texture2D = load_texture("ship.png").await.expect("Couldn't load file");
-        let heart_texture = load_texture("heart.png").await.expect("Couldn't load file");
-        let heart2_texture = load_texture("heart2.png")
-            .await
-            .expect("Couldn't load file");
-        let ammo_texture = load_texture("ammo.png").await.expect("Couldn't load file");
-        let ammo2_texture = load_texture("ammo2.png")
-            .await
-            .expect("Couldn't load file");
-        ship_texture.set_filter(FilterMode::Nearest);
+        Ship {
+            ship,
+            nb_ammo: NB_AMMO,
+            animation: 0,
+        }
+    }
+
+    pub fn reset(&mut self, width: f32, height: f32) {
+        self.ship.x = width / 2.0;
+        self.ship.y = height / 2.0;
+        self.ship.save_position();
+        self.nb_ammo = NB_AMMO;
+        self.ship.life = NB_LIFE;
+        self.animation = 0;
+    }
+
+    pub fn update(&mut self, delta_time: f32, input: &InputState, width: f32, height: f32) {
+        // vitesse proportionnelle à l'inclinaison du stick (maximale au clavier)
+        self.ship.x += input.movement.x * self.ship.speed * delta_time;
+        self.ship.y += input.movement.y * self.ship.speed * delta_time;
+        self.animation = if input.movement.x < 0.0 {
+            1
+        } else if input.movement.x > 0.0 {
+            2
+        } else {
+            0
+        };
+        // on s'assure qu'on ne déborde pas de l'écran
+        self.ship.x = clamp(self.ship.x, self.ship.size, width - self.ship.size);
+        self.ship.y = clamp(self.ship.y, self.ship.size, height - self.ship.size - 110.0);
+    }
+
+    // tir vers le haut, ou vers le point visé à la souris
+    pub fn shoot(&self, aim: Option<Vec2>) -> Shape {
+        let origin = vec2(self.ship.x, self.ship.y - 24.0);
+        let direction = aim
+            .map(|target| (target - origin).normalize_or(vec2(0.0, -1.0)))
+            .unwrap_or(vec2(0.0, -1.0));
+        let speed = self.ship.speed * 2.0;
+        Shape {
+            x: origin.x,
+            y: origin.y,
+            speed,
+            size: 32.0,
+            collided: false,
+            life: 0,
+            prev_x: origin.x,
+            prev_y: origin.y,
+            velocity: direction * speed,
+            kind: 0,
+            // le sprite pointe vers le haut, on le tourne dans le sens du tir
+            collider: BOLT
+                .to_collider(16.0, 16.0, 2.0, None)
+                .rotated(direction.x.atan2(-direction.y)),
+        }
+    }
+}
+
+impl ShipView {
+    pub fn new(assets: &Assets) -> ShipView {
+        let ship_texture = assets.sprite("ship.png");
+        let heart_texture = assets.sprite("heart.png");
+        let heart2_texture = assets.sprite("heart2.png");
+        let ammo_texture = assets.sprite("ammo.png");
+        let ammo2_texture = assets.sprite("ammo2.png");
 
         let ship_sprite = AnimatedSprite::new(
             16,
@@ -69,69 +160,56 @@ impl Ship {
             true,
         );
 
-        let sound_laser = load_sound("laser.wav").await.unwrap();
-        let nb_ammo=NB_AMMO;
-
-        Ship {
-            ship,
+        ShipView {
             ship_sprite,
-            sound_laser,
             ship_texture,
             heart_texture,
             heart2_texture,
             ammo_texture,
             ammo2_texture,
-            nb_ammo
         }
     }
 
-    pub fn reset(&mut self) {
-        self.ship.x = screen_width() / 2.0;
-        self.ship.y = screen_height() / 2.0;
-        self.nb_ammo=NB_AMMO;
-        self.ship.life=NB_LIFE;
-    }
-
-    fn display_life(&self){
+    fn display_life(&self, ship: &Ship) {
         let start_x = 180.0;
         let y = 20.0;
         let spacing = 20.0;
 
         for i in 0..NB_LIFE {
             let x = start_x + (i as f32 * spacing);
-            let texture = if i < self.ship.life {
+            let texture = if i < ship.ship.life {
                 &self.heart_texture
             } else {
                 &self.heart2_texture
             };
-            draw_texture(texture, x, y, WHITE);
+            texture.draw(x, y, WHITE, DrawTextureParams::default());
         }
     }
 
-    fn display_ammo(&self){
+    fn display_ammo(&self, ship: &Ship) {
         let start_x = 182.0;
         let y = 40.0;
         let spacing = 22.0;
 
-        for i in 0..NB_AMMO/20 {
+        for i in 0..NB_AMMO / 20 {
             let x = start_x + (i as f32 * spacing);
-            let texture = if i < self.nb_ammo/20 {
+            let texture = if i < ship.nb_ammo / 20 {
                 &self.ammo_texture
             } else {
                 &self.ammo2_texture
             };
-            draw_texture(texture, x, y, WHITE);
+            texture.draw(x, y, WHITE, DrawTextureParams::default());
         }
     }
 
-    pub fn display(&self) {
-        self.display_life();
-        self.display_ammo();
+    pub fn display(&self, ship: &Ship, alpha: f32) {
+        self.display_life(ship);
+        self.display_ammo(ship);
         let ship_frame = self.ship_sprite.frame();
-        draw_texture_ex(
-            &self.ship_texture,
-            self.ship.x - ship_frame.dest_size.x,
-            self.ship.y - ship_frame.dest_size.y,
+        let position = ship.ship.lerp_position(alpha);
+        self.ship_texture.draw(
+            position.x - ship_frame.dest_size.x,
+            position.y - ship_frame.dest_size.y,
             WHITE,
             DrawTextureParams {
                 dest_size: Some(ship_frame.dest_size * 2.0),
@@ -141,46 +219,9 @@ impl Ship {
         );
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.ship_sprite.set_animation(0);
-        if is_key_down(KeyCode::Right) {
-            self.ship.x += self.ship.speed * delta_time;
-            self.ship_sprite.set_animation(2);
-        }
-        if is_key_down(KeyCode::Left) {
-            self.ship.x -= self.ship.speed * delta_time;
-            self.ship_sprite.set_animation(1);
-        }
-        if is_key_down(KeyCode::Down) {
-            self.ship.y += self.ship.speed * delta_time;
-        }
-        if is_key_down(KeyCode::Up) {
-            self.ship.y -= self.ship.speed * delta_time;
-        }
-        // on s'assure qu'on ne déborde pas de l'écran
-        self.ship.x = clamp(self.ship.x, self.ship.size, screen_width() - self.ship.size);
-        self.ship.y = clamp(
-            self.ship.y,
-            self.ship.size,
-            screen_height() - self.ship.size - 110.0,
-        );
-        self.display();
+    // mise à jour de l'animation selon la direction du vaisseau
+    pub fn update(&mut self, ship: &Ship) {
+        self.ship_sprite.set_animation(ship.animation);
         self.ship_sprite.update();
     }
-
-    pub fn shoot(&self) -> Shape {
-        play_sound_once(&self.sound_laser);
-        Shape {
-            x: self.ship.x,
-            y: self.ship.y - 24.0,
-            speed: self.ship.speed * 2.0,
-            size: 32.0,
-            collided: false,
-            life: 0,
-        }
-    }
-
-    pub fn get_shape(&mut self) -> &mut Shape {
-        &mut self.ship
-    }
 }
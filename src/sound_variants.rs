@@ -0,0 +1,192 @@
+/* Structure SoundVariants (versions d'un bruitage placées en stéréo et en hauteur, calculées au chargement) */
+
+use macroquad::audio::{Sound, load_sound_from_bytes};
+use macroquad::rand;
+
+// fréquence d'échantillonnage des fichiers WAV produits
+const SAMPLE_RATE: u32 = 44100;
+// positions stéréo précalculées, de la gauche (-1) à la droite (1)
+const PANS: [f32; 5] = [-1.0, -0.5, 0.0, 0.5, 1.0];
+// légères variations de hauteur, tirées au hasard à chaque son
+const PITCHES: [f32; 3] = [0.94, 1.0, 1.06];
+// durée supposée d'un son dont on ne connaît pas les échantillons
+const UNKNOWN_DURATION: f32 = 1.0;
+
+// une version choisie : le son, son rang parmi les versions et sa durée en secondes
+#[derive(Clone)]
+pub struct Variant {
+    pub sound: Sound,
+    pub index: usize,
+    pub duration: f32,
+}
+
+// macroquad ne sait régler que le volume d'un son : la position et la hauteur
+// sont donc calculées d'avance, une version par couple (position, hauteur)
+pub struct SoundVariants {
+    // rangées par position puis par hauteur, avec leur durée
+    variants: Vec<(Sound, f32)>,
+    pans: &'static [f32],
+    pitches: &'static [f32],
+    // le son d'origine, si le fichier n'est pas un WAV PCM 16 bits
+    fallback: Sound,
+}
+
+impl SoundVariants {
+    pub async fn new(bytes: Option<&[u8]>, fallback: Sound) -> SoundVariants {
+        SoundVariants::build(bytes, fallback, &PANS, &PITCHES).await
+    }
+
+    // une seule version, au centre et à sa hauteur d'origine (sons de l'interface)
+    pub async fn centered(bytes: Option<&[u8]>, fallback: Sound) -> SoundVariants {
+        SoundVariants::build(bytes, fallback, &[0.0], &[1.0]).await
+    }
+
+    async fn build(
+        bytes: Option<&[u8]>,
+        fallback: Sound,
+        pans: &'static [f32],
+        pitches: &'static [f32],
+    ) -> SoundVariants {
+        let mut variants = vec![];
+        if let Some((channels, samples)) = bytes.and_then(decode_wav) {
+            for &pan in pans {
+                for &pitch in pitches {
+                    let stereo = place(&samples, channels, pan, pitch);
+                    let duration = stereo.len() as f32 / 2.0 / SAMPLE_RATE as f32;
+                    match load_sound_from_bytes(&encode_wav(2, &stereo)).await {
+                        Ok(sound) => variants.push((sound, duration)),
+                        Err(_) => return SoundVariants::plain(fallback),
+                    }
+                }
+            }
+        }
+        SoundVariants {
+            variants,
+            pans,
+            pitches,
+            fallback,
+        }
+    }
+
+    fn plain(fallback: Sound) -> SoundVariants {
+        SoundVariants {
+            variants: vec![],
+            pans: &[],
+            pitches: &[],
+            fallback,
+        }
+    }
+
+    // version la plus proche de la position voulue (-1 à gauche, 1 à droite), hauteur au hasard
+    pub fn pick(&self, pan: f32) -> Variant {
+        if self.variants.is_empty() {
+            return Variant {
+                sound: self.fallback.clone(),
+                index: 0,
+                duration: UNKNOWN_DURATION,
+            };
+        }
+        let step = 2.0 / (self.pans.len().max(2) - 1) as f32;
+        let position = ((pan.clamp(-1.0, 1.0) + 1.0) / step).round() as usize;
+        let position = position.min(self.pans.len() - 1);
+        let index = position * self.pitches.len() + rand::gen_range(0, self.pitches.len());
+        let (sound, duration) = &self.variants[index];
+        Variant {
+            sound: sound.clone(),
+            index,
+            duration: *duration,
+        }
+    }
+}
+
+// échantillons stéréo entrelacés d'un son placé en `pan` et rééchantillonné pour changer sa hauteur ;
+// au centre les deux voies gardent le volume d'origine, sur un côté l'autre voie s'éteint
+fn place(samples: &[i16], channels: u16, pan: f32, pitch: f32) -> Vec<i16> {
+    let channels = channels as usize;
+    let frames = samples.len() / channels;
+    let left_gain = (1.0 - pan).min(1.0);
+    let right_gain = (1.0 + pan).min(1.0);
+    // une voie d'un échantillon, la dernière voie servant pour les fichiers mono
+    let sample =
+        |frame: usize, channel: usize| samples[frame * channels + channel.min(channels - 1)] as f32;
+    let length = (frames as f32 / pitch) as usize;
+    let mut stereo = Vec::with_capacity(length * 2);
+    for i in 0..length {
+        // interpolation linéaire entre les deux échantillons d'origine les plus proches
+        let position = i as f32 * pitch;
+        let frame = (position as usize).min(frames - 1);
+        let next = (frame + 1).min(frames - 1);
+        let t = position - frame as f32;
+        for (channel, gain) in [(0, left_gain), (1, right_gain)] {
+            let value = sample(frame, channel) * (1.0 - t) + sample(next, channel) * t;
+            stereo.push((value * gain).round() as i16);
+        }
+    }
+    stereo
+}
+
+// voies et échantillons d'un fichier WAV PCM 16 bits ; None pour les autres formats
+fn decode_wav(bytes: &[u8]) -> Option<(u16, Vec<i16>)> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let u16_at = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let mut channels = None;
+    let mut at = 12;
+    // parcours des blocs : "fmt " décrit le format, "data" contient les échantillons
+    while at + 8 <= bytes.len() {
+        let size = u32_at(at + 4)? as usize;
+        let body = at + 8;
+        match bytes.get(at..at + 4)? {
+            b"fmt " => {
+                let format = u16_at(body)?;
+                let rate = u32_at(body + 4)?;
+                let bits = u16_at(body + 14)?;
+                if format != 1 || bits != 16 || rate != SAMPLE_RATE {
+                    return None;
+                }
+                channels = Some(u16_at(body + 2)?).filter(|&channels| channels > 0);
+            }
+            b"data" => {
+                let channels = channels?;
+                let data = bytes.get(body..(body + size).min(bytes.len()))?;
+                let samples: Vec<i16> = data
+                    .chunks_exact(2)
+                    .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                if samples.len() < channels as usize {
+                    return None;
+                }
+                return Some((channels, samples));
+            }
+            _ => {}
+        }
+        // les blocs de taille impaire sont suivis d'un octet de remplissage
+        at = body + size + size % 2;
+    }
+    None
+}
+
+// fichier WAV PCM 16 bits, 44100 Hz, à partir d'échantillons entrelacés
+pub fn encode_wav(channels: u16, samples: &[i16]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let block_align = channels * 2;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
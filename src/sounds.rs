@@ -0,0 +1,136 @@
+/* Structure SoundEffects (bruitages du jeu) */
+
+use crate::Shape;
+use crate::assets::Assets;
+use crate::events::*;
+use crate::mixer::*;
+use crate::sound_variants::*;
+use macroquad::prelude::*;
+
+// taille d'explosion jouée à plein volume ; les plus petites sont plus discrètes
+const LOUDEST_EXPLOSION_SIZE: f32 = 64.0;
+const QUIETEST_EXPLOSION: f32 = 0.35;
+// voix jouées en même temps, par son
+const LASER_VOICES: usize = 4;
+const EXPLOSION_VOICES: usize = 6;
+const CLICK_VOICES: usize = 2;
+
+pub struct SoundEffects {
+    laser: SoundVariants,
+    explosion: SoundVariants,
+    // son de l'interface, tiré du laser mais compté à part
+    click: SoundVariants,
+    // sons demandés par les événements, passés au mixer par flush
+    pending: Vec<SoundRequest>,
+    // une grosse explosion fait baisser la musique
+    duck: bool,
+    // largeur de la zone de jeu, pour placer les sons en stéréo
+    pub width: f32,
+}
+
+impl SoundEffects {
+    pub async fn new(assets: &Assets) -> SoundEffects {
+        let variants =
+            |file: &str| SoundVariants::new(assets.sound_bytes(file), assets.sound(file));
+        SoundEffects {
+            laser: variants("laser.wav").await,
+            explosion: variants("explosion.wav").await,
+            click: SoundEffects::click_variants(assets).await,
+            pending: vec![],
+            duck: false,
+            width: screen_width(),
+        }
+    }
+
+    // bruitage modifié (rechargement à chaud) : seules ses versions sont recalculées
+    pub async fn reload(&mut self, assets: &Assets, file: &str) {
+        if file == "laser.wav" {
+            self.click = SoundEffects::click_variants(assets).await;
+        }
+        let variants = match file {
+            "laser.wav" => &mut self.laser,
+            "explosion.wav" => &mut self.explosion,
+            _ => return,
+        };
+        *variants = SoundVariants::new(assets.sound_bytes(file), assets.sound(file)).await;
+    }
+
+    // ses propres sons : couper un clic ne coupe pas un tir qui joue la même version
+    async fn click_variants(assets: &Assets) -> SoundVariants {
+        SoundVariants::centered(assets.sound_bytes("laser.wav"), assets.sound("laser.wav")).await
+    }
+
+    // son de l'interface (validation dans les menus)
+    pub fn click(&mut self) {
+        self.pending.push(SoundRequest {
+            variant: self.click.pick(0.0),
+            group: "click",
+            voices: CLICK_VOICES,
+            bus: Bus::Ui,
+            volume: 1.0,
+            priority: Priority::High,
+        });
+    }
+
+    // position stéréo d'après l'abscisse dans la zone de jeu, de -1 (gauche) à 1 (droite)
+    fn pan(&self, x: f32) -> f32 {
+        x / self.width * 2.0 - 1.0
+    }
+
+    fn explode(&mut self, shape: &Shape, volume: f32, priority: Priority) {
+        self.pending.push(SoundRequest {
+            variant: self.explosion.pick(self.pan(shape.x)),
+            group: "explosion.wav",
+            voices: EXPLOSION_VOICES,
+            bus: Bus::Sfx,
+            volume,
+            priority,
+        });
+    }
+
+    // volume d'une explosion d'après sa taille
+    fn explosion_volume(shape: &Shape) -> f32 {
+        (shape.size / LOUDEST_EXPLOSION_SIZE).clamp(QUIETEST_EXPLOSION, 1.0)
+    }
+
+    pub fn flush(&mut self, mixer: &mut Mixer) {
+        for request in self.pending.drain(..) {
+            mixer.play(request);
+        }
+        if self.duck {
+            mixer.duck();
+            self.duck = false;
+        }
+    }
+}
+
+// le son du tir et celui des explosions, placés en stéréo ; le vaisseau touché et
+// les bâtiments passent avant les ennemis quand tout explose en même temps
+impl Subscriber for SoundEffects {
+    fn notify(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::ShotFired { x } => self.pending.push(SoundRequest {
+                variant: self.laser.pick(self.pan(*x)),
+                group: "laser.wav",
+                voices: LASER_VOICES,
+                bus: Bus::Sfx,
+                volume: 1.0,
+                priority: Priority::Normal,
+            }),
+            GameEvent::EnemyDestroyed { enemy, .. } => {
+                self.explode(enemy, SoundEffects::explosion_volume(enemy), Priority::Low)
+            }
+            GameEvent::BuildingDamaged { enemy } => self.explode(
+                enemy,
+                SoundEffects::explosion_volume(enemy),
+                Priority::Normal,
+            ),
+            GameEvent::ShipHit { enemy, .. } => {
+                self.explode(enemy, 1.0, Priority::High);
+                self.duck = true;
+            }
+            GameEvent::BuildingDestroyed { .. } => self.duck = true,
+            _ => {}
+        }
+    }
+}
@@ -1,10 +1,11 @@
+use crate::waves::*;
 use macroquad::prelude::*;
 
-pub fn display_game_over(font: &Font) {
+pub fn display_game_over(font: Option<&Font>, prompt: &str) {
     let text = "GAME OVER!";
     let text_params = TextParams {
         font_size: 50,
-        font: Some(font),
+        font,
         color: RED,
         font_scale: 1.0,
         ..Default::default()
@@ -21,20 +22,19 @@ pub fn display_game_over(font: &Font) {
         screen_height() / 2.0 + text_dimensions.height / 2.0,
         text_params.clone(),
     );
-    let text = "Press LeftShift to continue ...";
     draw_text_ex(
-        text,
+        prompt,
         screen_width() / 2.0 - text_dimensions.width,
         screen_height() / 2.0 + text_dimensions.height * 2.0,
         text_params.clone(),
     );
 }
 
-pub fn display_won(font: &Font) {
+pub fn display_won(font: Option<&Font>, prompt: &str) {
     let text = "Congratulations, you won!";
     let text_params = TextParams {
         font_size: 50,
-        font: Some(font),
+        font,
         color: YELLOW,
         font_scale: 1.0,
         ..Default::default()
@@ -51,9 +51,8 @@ pub fn display_won(font: &Font) {
         screen_height() / 2.0 + text_dimensions.height / 2.0,
         text_params.clone(),
     );
-    let text = "Press LeftShift to continue ...";
     draw_text_ex(
-        text,
+        prompt,
         screen_width() / 2.0 - text_dimensions.width / 2.0,
         screen_height() / 2.0 + text_dimensions.height * 2.0,
         text_params.clone(),
@@ -83,3 +82,109 @@ pub fn display_game_name() {
         YELLOW,
     );
 }
+
+// messages d'erreur en bas de l'écran, le dernier tout en bas
+pub fn display_errors(errors: &[String]) {
+    for (i, text) in errors.iter().rev().enumerate() {
+        draw_text(
+            text,
+            10.0,
+            screen_height() - 10.0 - i as f32 * 20.0,
+            18.0,
+            RED,
+        );
+    }
+}
+
+// texte centré horizontalement
+pub fn draw_centered(text: &str, y: f32, text_params: &TextParams) {
+    let text_dimensions = measure_text(
+        text,
+        text_params.font,
+        text_params.font_size,
+        text_params.font_scale,
+    );
+    draw_text_ex(
+        text,
+        screen_width() / 2.0 - text_dimensions.width / 2.0,
+        y,
+        text_params.clone(),
+    );
+}
+
+// annonce "Wave N" au début d'une vague, bilan à la fin
+pub fn display_wave(font: Option<&Font>, waves: &Waves) {
+    let mut text_params = TextParams {
+        font_size: 50,
+        font,
+        color: YELLOW,
+        font_scale: 1.0,
+        ..Default::default()
+    };
+    let y = screen_height() / 2.0 - 60.0;
+    match (waves.phase, waves.summary) {
+        (WavePhase::Intro(_), _) => {
+            draw_centered(&format!("Wave {}", waves.number()), y, &text_params);
+            text_params.font_size = 25;
+            text_params.color = WHITE;
+            draw_centered(&format!("Level {}", waves.level), y + 40.0, &text_params);
+        }
+        (WavePhase::Breather(_), Some(summary)) => {
+            draw_centered(&format!("Wave {} cleared", summary.wave), y, &text_params);
+            text_params.font_size = 25;
+            text_params.color = WHITE;
+            let destroyed = format!("Destroyed: {} / {}", summary.destroyed, summary.count);
+            draw_centered(&destroyed, y + 40.0, &text_params);
+            draw_centered(
+                &format!("Score: +{}", summary.score),
+                y + 70.0,
+                &text_params,
+            );
+        }
+        _ => {}
+    }
+}
+
+// écran de chargement : barre de progression et fichier en cours
+pub fn display_loading(loaded: usize, total: usize, file: &str) {
+    display_game_name();
+    let width = screen_width() * 0.6;
+    let x = (screen_width() - width) / 2.0;
+    let y = screen_height() / 2.0;
+    let progress = loaded as f32 / total.max(1) as f32;
+    draw_rectangle_lines(x, y, width, 20.0, 2.0, WHITE);
+    draw_rectangle(x + 4.0, y + 4.0, (width - 8.0) * progress, 12.0, YELLOW);
+    let text = format!("Loading {} ({}/{})", file, loaded, total);
+    let text_dimensions = measure_text(&text, None, 20, 1.0);
+    draw_text(
+        &text,
+        screen_width() / 2.0 - text_dimensions.width / 2.0,
+        y + 50.0,
+        20.0,
+        WHITE,
+    );
+}
+
+// petit encadré en bas à droite : les fichiers remplacés par un damier, un son muet...
+pub fn display_asset_warnings(errors: &[String]) {
+    if errors.is_empty() {
+        return;
+    }
+    let title = "Missing assets:";
+    let width = errors
+        .iter()
+        .map(|text| measure_text(text, None, 16, 1.0).width)
+        .fold(measure_text(title, None, 16, 1.0).width, f32::max)
+        + 20.0;
+    let height = (errors.len() + 1) as f32 * 16.0 + 12.0;
+    let x = screen_width() - width - 10.0;
+    let y = screen_height() - height - 10.0;
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_rectangle_lines(x, y, width, height, 1.0, ORANGE);
+    for (i, text) in std::iter::once(title)
+        .chain(errors.iter().map(|text| text.as_str()))
+        .enumerate()
+    {
+        draw_text(text, x + 10.0, y + 20.0 + i as f32 * 16.0, 16.0, ORANGE);
+    }
+}
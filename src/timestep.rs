@@ -0,0 +1,47 @@
+/* Structure FixedTimestep (pas de simulation fixe) */
+
+// la simulation avance toujours par pas de 1/120e de seconde
+pub const STEP: f32 = 1.0 / 120.0;
+// au delà, on abandonne le retard plutôt que de geler le jeu
+const MAX_STEPS_PER_FRAME: u32 = 8;
+// vitesse des animations, en images par seconde ; elles suivent le temps de la simulation
+// pour que les masques de collision soient ceux des images affichées
+pub const ANIMATION_FPS: f32 = 12.0;
+
+pub struct FixedTimestep {
+    accumulator: f32,
+    pub steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new() -> FixedTimestep {
+        FixedTimestep {
+            accumulator: 0.0,
+            steps: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.steps = 0;
+    }
+
+    // ajoute le temps de la frame et retourne le nombre de pas à simuler
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time;
+        let mut steps = (self.accumulator / STEP) as u32;
+        if steps > MAX_STEPS_PER_FRAME {
+            steps = MAX_STEPS_PER_FRAME;
+            self.accumulator = 0.0;
+        } else {
+            self.accumulator -= steps as f32 * STEP;
+        }
+        self.steps = steps;
+        steps
+    }
+
+    // position entre les deux derniers pas, pour interpoler l'affichage
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / STEP).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,172 @@
+/* Structure TouchControls (commandes tactiles pour la version web) */
+
+use crate::settings::Settings;
+use macroquad::prelude::*;
+
+const STICK_RADIUS: f32 = 60.0;
+const FIRE_RADIUS: f32 = 50.0;
+const SMALL_RADIUS: f32 = 28.0;
+// intervalle entre deux tirs en tir automatique
+const AUTO_FIRE_INTERVAL: f32 = 0.15;
+
+// ce que les doigts demandent pendant cette frame
+#[derive(Clone, Default)]
+pub struct TouchState {
+    // un écran tactile a été utilisé : on affiche les commandes
+    pub active: bool,
+    pub stick: Vec2,
+    pub fire: bool,
+    pub pause: bool,
+    // un doigt vient de toucher l'écran (pour valider les écrans de fin)
+    pub tapped: bool,
+}
+
+pub struct TouchControls {
+    pub state: TouchState,
+    stick_touch: Option<u64>,
+    fire_touch: Option<u64>,
+    fire_timer: f32,
+}
+
+impl TouchControls {
+    pub fn new() -> TouchControls {
+        TouchControls {
+            state: TouchState::default(),
+            stick_touch: None,
+            fire_touch: None,
+            fire_timer: 0.0,
+        }
+    }
+
+    // les commandes sont placées par rapport aux bords de l'écran
+    fn stick_center() -> Vec2 {
+        vec2(STICK_RADIUS + 30.0, screen_height() - STICK_RADIUS - 30.0)
+    }
+
+    fn fire_center() -> Vec2 {
+        vec2(
+            screen_width() - FIRE_RADIUS - 30.0,
+            screen_height() - FIRE_RADIUS - 30.0,
+        )
+    }
+
+    fn auto_fire_center() -> Vec2 {
+        TouchControls::fire_center() - vec2(0.0, FIRE_RADIUS + SMALL_RADIUS + 20.0)
+    }
+
+    fn pause_center() -> Vec2 {
+        vec2(screen_width() - SMALL_RADIUS - 10.0, 80.0)
+    }
+
+    // lecture des doigts posés sur l'écran, une fois par frame
+    pub fn update(&mut self, settings: &mut Settings) {
+        let touches = touches();
+        let mut fire = false;
+        self.state.pause = false;
+        self.state.tapped = false;
+        for touch in &touches {
+            self.state.active = true;
+            if touch.phase != TouchPhase::Started {
+                continue;
+            }
+            self.state.tapped = true;
+            let position = touch.position;
+            if position.distance(TouchControls::stick_center()) < STICK_RADIUS * 2.0 {
+                self.stick_touch = Some(touch.id);
+            } else if position.distance(TouchControls::fire_center()) < FIRE_RADIUS * 1.5 {
+                self.fire_touch = Some(touch.id);
+                self.fire_timer = AUTO_FIRE_INTERVAL;
+                fire = true;
+            } else if position.distance(TouchControls::auto_fire_center()) < SMALL_RADIUS * 1.5 {
+                settings.auto_fire = !settings.auto_fire;
+                settings.save();
+            } else if position.distance(TouchControls::pause_center()) < SMALL_RADIUS * 1.5 {
+                self.state.pause = true;
+            }
+        }
+
+        // le doigt du stick donne la direction, proportionnelle à l'écart au centre
+        let stick = self.stick_touch.and_then(|id| {
+            touches
+                .iter()
+                .find(|touch| touch.id == id && is_held(touch))
+        });
+        self.state.stick = match stick {
+            Some(touch) => {
+                let offset = (touch.position - TouchControls::stick_center()) / STICK_RADIUS;
+                offset.clamp_length_max(1.0)
+            }
+            None => {
+                self.stick_touch = None;
+                Vec2::ZERO
+            }
+        };
+
+        // tir automatique tant que le bouton reste enfoncé
+        let fire_held = self
+            .fire_touch
+            .is_some_and(|id| touches.iter().any(|touch| touch.id == id && is_held(touch)));
+        if !fire_held {
+            self.fire_touch = None;
+        } else if settings.auto_fire {
+            self.fire_timer -= get_frame_time();
+            if self.fire_timer <= 0.0 {
+                self.fire_timer = AUTO_FIRE_INTERVAL;
+                fire = true;
+            }
+        }
+        self.state.fire = fire;
+    }
+
+    // affichage par dessus le HUD
+    pub fn display(&self, auto_fire: bool) {
+        if !self.state.active {
+            return;
+        }
+        let color = Color::new(1.0, 1.0, 1.0, 0.35);
+        let stick_center = TouchControls::stick_center();
+        draw_circle_lines(stick_center.x, stick_center.y, STICK_RADIUS, 3.0, color);
+        let knob = stick_center + self.state.stick * STICK_RADIUS;
+        draw_circle(knob.x, knob.y, STICK_RADIUS / 2.5, color);
+
+        let fire_center = TouchControls::fire_center();
+        let fire_color = if self.fire_touch.is_some() {
+            Color::new(1.0, 0.3, 0.3, 0.6)
+        } else {
+            color
+        };
+        draw_circle(fire_center.x, fire_center.y, FIRE_RADIUS, fire_color);
+        draw_text(
+            "FIRE",
+            fire_center.x - 24.0,
+            fire_center.y + 7.0,
+            24.0,
+            WHITE,
+        );
+
+        let auto_center = TouchControls::auto_fire_center();
+        let auto_color = if auto_fire { YELLOW } else { color };
+        draw_circle_lines(auto_center.x, auto_center.y, SMALL_RADIUS, 2.0, auto_color);
+        draw_text(
+            "AUTO",
+            auto_center.x - 18.0,
+            auto_center.y + 5.0,
+            16.0,
+            auto_color,
+        );
+
+        let pause_center = TouchControls::pause_center();
+        draw_circle_lines(pause_center.x, pause_center.y, SMALL_RADIUS, 2.0, color);
+        draw_text(
+            "II",
+            pause_center.x - 8.0,
+            pause_center.y + 8.0,
+            24.0,
+            color,
+        );
+    }
+}
+
+fn is_held(touch: &Touch) -> bool {
+    !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled)
+}
@@ -0,0 +1,493 @@
+/* Structure Waves (vagues d'ennemis et progression des niveaux) */
+
+use crate::archetypes::Archetypes;
+use crate::data::*;
+use crate::enemies::EnemiesSet;
+use crate::events::*;
+use crate::settings::Difficulty;
+use macroquad::rand::RandGenerator;
+use nanoserde::DeRon;
+
+pub const DEFAULT_WAVES_FILE: &str = "waves.ron";
+// les vagues livrées avec le jeu
+const DEFAULT_WAVES: &str = include_str!("../assets/waves.ron");
+// durée de l'annonce "Wave N", en secondes
+const WAVE_INTRO: f32 = 2.0;
+
+#[derive(DeRon, Clone)]
+pub struct MixDef {
+    pub archetype: String,
+    pub weight: f32,
+}
+
+#[derive(DeRon, Clone)]
+pub struct WaveDef {
+    pub count: u32,
+    pub spawn_rate: f32,
+    pub breather: f32,
+    pub mix: Vec<MixDef>,
+}
+
+#[derive(DeRon)]
+struct WavesFile {
+    levels: u32,
+    count_ramp: f32,
+    rate_ramp: f32,
+    speed_ramp: f32,
+    waves: Vec<WaveDef>,
+}
+
+// une vague vérifiée, les types d'ennemis étant désignés par leur index
+#[derive(Clone)]
+pub struct Wave {
+    pub count: u32,
+    pub spawn_rate: f32,
+    pub breather: f32,
+    pub mix: Vec<(usize, f32)>,
+}
+
+#[derive(Clone)]
+pub struct WaveList {
+    pub levels: u32,
+    pub count_ramp: f32,
+    pub rate_ramp: f32,
+    pub speed_ramp: f32,
+    pub waves: Vec<Wave>,
+}
+
+// bilan affiché à la fin d'une vague
+#[derive(Clone, Copy)]
+pub struct WaveSummary {
+    pub level: u32,
+    pub wave: usize,
+    pub destroyed: u32,
+    pub count: u32,
+    pub score: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WavePhase {
+    // annonce de la vague, temps restant
+    Intro(f32),
+    Spawning,
+    // attente de la fin des ennemis encore à l'écran
+    Clearing,
+    // pause entre deux vagues, temps restant
+    Breather(f32),
+    Finished,
+}
+
+// déroulement des vagues pendant une partie
+pub struct Waves {
+    pub list: WaveList,
+    pub level: u32,
+    pub wave: usize,
+    pub phase: WavePhase,
+    pub summary: Option<WaveSummary>,
+    spawned: u32,
+    destroyed: u32,
+    start_score: u32,
+    // réglage du joueur, choisi au début de la partie
+    difficulty: Difficulty,
+}
+
+impl Wave {
+    // tirage d'un type d'ennemi selon le mélange de la vague
+    fn pick(&self, rng: &RandGenerator) -> usize {
+        let total: f32 = self.mix.iter().map(|(_, weight)| weight).sum();
+        let mut choice = rng.gen_range(0.0, total);
+        for &(kind, weight) in &self.mix {
+            if choice < weight {
+                return kind;
+            }
+            choice -= weight;
+        }
+        self.mix[self.mix.len() - 1].0
+    }
+}
+
+impl WaveList {
+    // lecture et vérification d'un fichier de vagues
+    pub fn parse(file: &str, text: &str, archetypes: &Archetypes) -> Result<WaveList, DataError> {
+        let data =
+            WavesFile::deserialize_ron(text).map_err(|e| DataError::Parse(file.to_string(), e))?;
+        if data.waves.is_empty() {
+            return Err(DataError::Empty(file.to_string()));
+        }
+        let mut waves = vec![];
+        for (i, def) in data.waves.iter().enumerate() {
+            let invalid = |message: String| DataError::Invalid {
+                file: file.to_string(),
+                entry: i,
+                name: format!("wave {}", i + 1),
+                message,
+            };
+            if def.count == 0 {
+                return Err(invalid("count must be greater than 0".to_string()));
+            }
+            if !def.spawn_rate.is_finite() || def.spawn_rate <= 0.0 {
+                return Err(invalid("spawn_rate must be greater than 0".to_string()));
+            }
+            if !def.breather.is_finite() || def.breather < 0.0 {
+                return Err(invalid("breather must be a positive number".to_string()));
+            }
+            let mut mix = vec![];
+            for entry in &def.mix {
+                let Some(kind) = archetypes
+                    .list
+                    .iter()
+                    .position(|archetype| archetype.def.name == entry.archetype)
+                else {
+                    return Err(invalid(format!(
+                        "unknown archetype \"{}\"",
+                        entry.archetype
+                    )));
+                };
+                if !entry.weight.is_finite() || entry.weight <= 0.0 {
+                    return Err(invalid("weights must be greater than 0".to_string()));
+                }
+                mix.push((kind, entry.weight));
+            }
+            if mix.is_empty() {
+                mix = WaveList::default_mix(archetypes);
+            }
+            waves.push(Wave {
+                count: def.count,
+                spawn_rate: def.spawn_rate,
+                breather: def.breather,
+                mix,
+            });
+        }
+        let invalid = |message: &str| DataError::Invalid {
+            file: file.to_string(),
+            entry: 0,
+            name: "levels".to_string(),
+            message: message.to_string(),
+        };
+        if data.levels == 0 {
+            return Err(invalid("levels must be greater than 0"));
+        }
+        for ramp in [data.count_ramp, data.rate_ramp, data.speed_ramp] {
+            if !ramp.is_finite() || ramp <= 0.0 {
+                return Err(invalid("ramps must be greater than 0"));
+            }
+        }
+        Ok(WaveList {
+            levels: data.levels,
+            count_ramp: data.count_ramp,
+            rate_ramp: data.rate_ramp,
+            speed_ramp: data.speed_ramp,
+            waves,
+        })
+    }
+
+    // fréquences d'apparition données dans le fichier des types d'ennemis
+    fn default_mix(archetypes: &Archetypes) -> Vec<(usize, f32)> {
+        archetypes
+            .list
+            .iter()
+            .enumerate()
+            .map(|(kind, archetype)| (kind, archetype.def.weight))
+            .collect()
+    }
+
+    // les vagues livrées avec le jeu ; si elles ne correspondent pas aux types
+    // d'ennemis chargés, une seule vague de 50 ennemis
+    pub fn default_waves(archetypes: &Archetypes) -> WaveList {
+        WaveList::parse(DEFAULT_WAVES_FILE, DEFAULT_WAVES, archetypes).unwrap_or(WaveList {
+            levels: 1,
+            count_ramp: 1.0,
+            rate_ramp: 1.0,
+            speed_ramp: 1.0,
+            waves: vec![Wave {
+                count: 50,
+                spawn_rate: 3.0,
+                breather: 0.0,
+                mix: WaveList::default_mix(archetypes),
+            }],
+        })
+    }
+
+    // chargement depuis le dossier assets ; en cas d'erreur on garde les vagues par défaut
+    pub async fn load(file: &str, archetypes: &Archetypes) -> (WaveList, Option<DataError>) {
+        load_or_default(
+            file,
+            |text| WaveList::parse(file, text, archetypes),
+            || WaveList::default_waves(archetypes),
+        )
+        .await
+    }
+}
+
+impl Waves {
+    pub fn new(list: WaveList) -> Waves {
+        Waves {
+            list,
+            level: 1,
+            wave: 0,
+            phase: WavePhase::Intro(WAVE_INTRO),
+            summary: None,
+            spawned: 0,
+            destroyed: 0,
+            start_score: 0,
+            difficulty: Difficulty::Normal,
+        }
+    }
+
+    // retour à la première vague du premier niveau
+    pub fn reset(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.level = 1;
+        self.start_wave(0, 0);
+        self.summary = None;
+    }
+
+    // vagues modifiées en cours de partie : on reste sur la même vague si elle existe encore
+    pub fn reload(&mut self, list: WaveList) {
+        self.list = list;
+        self.level = self.level.min(self.list.levels);
+        self.wave = self.wave.min(self.list.waves.len() - 1);
+    }
+
+    fn start_wave(&mut self, wave: usize, score: u32) {
+        self.wave = wave;
+        self.phase = WavePhase::Intro(WAVE_INTRO);
+        self.spawned = 0;
+        self.destroyed = 0;
+        self.start_score = score;
+    }
+
+    // multiplicateur de difficulté du niveau en cours
+    fn ramp(&self, factor: f32) -> f32 {
+        factor.powi(self.level as i32 - 1)
+    }
+
+    // nombre d'ennemis de la vague en cours, niveau compris
+    pub fn count(&self) -> u32 {
+        let wave = &self.list.waves[self.wave];
+        (wave.count as f32 * self.ramp(self.list.count_ramp)).round() as u32
+    }
+
+    // numéro de la vague affiché, compté depuis le début de la partie
+    pub fn number(&self) -> usize {
+        (self.level as usize - 1) * self.list.waves.len() + self.wave + 1
+    }
+
+    // dernière vague du dernier niveau, jouée sur la musique du boss
+    pub fn is_final_wave(&self) -> bool {
+        self.level == self.list.levels
+            && self.wave == self.list.waves.len() - 1
+            && !self.is_finished()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase == WavePhase::Finished
+    }
+
+    // avance la vague : apparition des ennemis, fin de vague et passage à la suivante
+    pub fn update(
+        &mut self,
+        delta_time: f32,
+        enemies: &mut EnemiesSet,
+        width: f32,
+        rng: &RandGenerator,
+        score: u32,
+    ) -> Option<WaveSummary> {
+        match self.phase {
+            WavePhase::Intro(time) if time > delta_time => {
+                self.phase = WavePhase::Intro(time - delta_time);
+            }
+            WavePhase::Intro(_) => {
+                self.summary = None;
+                self.phase = WavePhase::Spawning;
+            }
+            WavePhase::Spawning => {
+                let wave = &self.list.waves[self.wave];
+                let rate = wave.spawn_rate
+                    * self.ramp(self.list.rate_ramp)
+                    * self.difficulty.spawn_factor();
+                if rng.gen_range(0.0, 1.0) < rate * delta_time {
+                    let kind = wave.pick(rng);
+                    let speed_factor =
+                        self.ramp(self.list.speed_ramp) * self.difficulty.speed_factor();
+                    enemies.spawn(kind, speed_factor, width, rng);
+                    self.spawned += 1;
+                }
+                if self.spawned >= self.count() {
+                    self.phase = WavePhase::Clearing;
+                }
+            }
+            WavePhase::Clearing if enemies.enemies.is_empty() => {
+                let summary = WaveSummary {
+                    level: self.level,
+                    wave: self.number(),
+                    destroyed: self.destroyed,
+                    count: self.spawned,
+                    score: score - self.start_score,
+                };
+                self.summary = Some(summary);
+                self.phase = WavePhase::Breather(self.list.waves[self.wave].breather);
+                return Some(summary);
+            }
+            WavePhase::Clearing => {}
+            WavePhase::Breather(time) if time > delta_time => {
+                self.phase = WavePhase::Breather(time - delta_time);
+            }
+            WavePhase::Breather(_) => {
+                if self.wave + 1 < self.list.waves.len() {
+                    self.start_wave(self.wave + 1, score);
+                } else if self.level < self.list.levels {
+                    self.level += 1;
+                    self.start_wave(0, score);
+                } else {
+                    self.phase = WavePhase::Finished;
+                }
+            }
+            WavePhase::Finished => {}
+        }
+        None
+    }
+}
+
+// compte les ennemis détruits pendant la vague
+impl Subscriber for Waves {
+    fn notify(&mut self, event: &GameEvent) {
+        if let GameEvent::EnemyDestroyed { .. } = event {
+            self.destroyed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::masks::MaskLibrary;
+    use std::mem::discriminant;
+
+    const STEP: f32 = 1.0 / 120.0;
+
+    // deux niveaux de deux vagues ; un ennemi apparaît à chaque pas
+    const TWO_LEVELS: &str = r#"(
+        levels: 2,
+        count_ramp: 1.5,
+        rate_ramp: 1.0,
+        speed_ramp: 1.0,
+        waves: [
+            (count: 2, spawn_rate: 1000.0, breather: 0.5, mix: [(archetype: "small", weight: 1.0)]),
+            (count: 4, spawn_rate: 1000.0, breather: 0.5, mix: []),
+        ],
+    )"#;
+
+    struct Run {
+        waves: Waves,
+        enemies: EnemiesSet,
+        rng: RandGenerator,
+    }
+
+    impl Run {
+        fn new(text: &str) -> Run {
+            let archetypes = Archetypes::default_archetypes();
+            let list = WaveList::parse("test.ron", text, &archetypes).unwrap();
+            let mut waves = Waves::new(list);
+            waves.reset(Difficulty::Normal);
+            let rng = RandGenerator::new();
+            rng.srand(42);
+            Run {
+                waves,
+                enemies: EnemiesSet::new(archetypes, MaskLibrary::new()),
+                rng,
+            }
+        }
+
+        // pas de simulation jusqu'au changement de phase ; renvoie le bilan de fin de vague
+        fn next_phase(&mut self) -> Option<WaveSummary> {
+            let phase = discriminant(&self.waves.phase);
+            for _ in 0..10_000 {
+                let summary = self
+                    .waves
+                    .update(STEP, &mut self.enemies, 800.0, &self.rng, 100);
+                if discriminant(&self.waves.phase) != phase {
+                    return summary;
+                }
+            }
+            panic!("wave phase never changed");
+        }
+
+        // une vague complète, de l'annonce à la pause, tous les ennemis détruits d'un coup
+        fn play_wave(&mut self) -> WaveSummary {
+            assert!(matches!(self.waves.phase, WavePhase::Intro(_)));
+            self.next_phase();
+            assert!(self.waves.phase == WavePhase::Spawning);
+            self.next_phase();
+            assert!(self.waves.phase == WavePhase::Clearing);
+            assert_eq!(self.enemies.enemies.len() as u32, self.waves.count());
+            // tant qu'il reste des ennemis, la vague n'est pas finie
+            self.waves
+                .update(STEP, &mut self.enemies, 800.0, &self.rng, 100);
+            assert!(self.waves.phase == WavePhase::Clearing);
+            self.enemies.clear();
+            let summary = self.next_phase().unwrap();
+            assert!(matches!(self.waves.phase, WavePhase::Breather(_)));
+            self.next_phase();
+            summary
+        }
+    }
+
+    #[test]
+    fn waves_ramp_up_across_levels() {
+        let mut run = Run::new(TWO_LEVELS);
+        let mut seen = vec![];
+        while !run.waves.is_finished() {
+            let (level, wave) = (run.waves.level, run.waves.wave);
+            let (number, count) = (run.waves.number(), run.waves.count());
+            let final_wave = run.waves.is_final_wave();
+            let summary = run.play_wave();
+            assert_eq!(
+                (summary.level, summary.wave, summary.count),
+                (level, number, count)
+            );
+            seen.push((level, wave, number, count, final_wave));
+        }
+        // au niveau 2, 1.5 fois plus d'ennemis ; la dernière vague est celle du boss
+        assert_eq!(
+            seen,
+            vec![
+                (1, 0, 1, 2, false),
+                (1, 1, 2, 4, false),
+                (2, 0, 3, 3, false),
+                (2, 1, 4, 6, true),
+            ]
+        );
+        assert!(!run.waves.is_final_wave());
+        // une partie finie ne fait plus apparaître d'ennemis
+        for _ in 0..1000 {
+            run.waves
+                .update(STEP, &mut run.enemies, 800.0, &run.rng, 100);
+        }
+        assert!(run.waves.is_finished());
+        assert!(run.enemies.enemies.is_empty());
+    }
+
+    #[test]
+    fn reload_clamps_wave_and_level() {
+        let mut run = Run::new(TWO_LEVELS);
+        for _ in 0..3 {
+            run.play_wave();
+        }
+        assert_eq!((run.waves.level, run.waves.wave), (2, 1));
+        let archetypes = Archetypes::default_archetypes();
+        let shorter = TWO_LEVELS.replace("levels: 2", "levels: 1").replace(
+            "(count: 4, spawn_rate: 1000.0, breather: 0.5, mix: []),",
+            "",
+        );
+        let list = WaveList::parse("test.ron", &shorter, &archetypes).unwrap();
+        run.waves.reload(list);
+        assert_eq!((run.waves.level, run.waves.wave), (1, 0));
+        assert_eq!(run.waves.number(), 1);
+        assert!(run.waves.is_final_wave());
+        // le fichier plus long ne change pas la vague en cours
+        let list = WaveList::parse("test.ron", TWO_LEVELS, &archetypes).unwrap();
+        run.waves.reload(list);
+        assert_eq!((run.waves.level, run.waves.wave), (1, 0));
+    }
+}
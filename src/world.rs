@@ -0,0 +1,407 @@
+/* Structure World (simulation du jeu, sans affichage) */
+
+use crate::archetypes::Archetypes;
+use crate::buildings::*;
+use crate::bullets::*;
+use crate::enemies::*;
+use crate::events::*;
+use crate::grid::SpatialGrid;
+use crate::level::Level;
+use crate::masks::MaskLibrary;
+use crate::score::*;
+use crate::settings::Difficulty;
+use crate::ship::*;
+use crate::waves::*;
+use macroquad::prelude::*;
+
+// état des commandes pour une frame
+#[derive(Clone, Copy, Default)]
+pub struct InputState {
+    // direction voulue, chaque axe entre -1 et 1
+    pub movement: Vec2,
+    pub shoot: bool,
+    // point visé à la souris (en pixels entiers), sinon tir vers le haut
+    pub aim: Option<Vec2>,
+    pub pause: bool,
+}
+
+impl InputState {
+    // garde les appuis de la frame jusqu'au prochain pas de simulation
+    pub fn latch(&mut self, input: &InputState) {
+        self.movement = input.movement;
+        self.aim = input.aim;
+        self.shoot |= input.shoot;
+        self.pause |= input.pause;
+    }
+
+    // les appuis ne valent que pour un seul pas
+    pub fn consume_presses(&mut self) {
+        self.shoot = false;
+        self.pause = false;
+    }
+}
+
+// taille d'une case des grilles de collision, de l'ordre du plus gros ennemi
+const GRID_CELL_SIZE: f32 = 64.0;
+// nombre d'ennemis à l'écran qui fait monter la menace au maximum
+const THREAT_ENEMIES: f32 = 12.0;
+
+pub struct World {
+    pub seed: u64,
+    // générateur aléatoire du gameplay, séparé de celui des effets (particules...)
+    rng: rand::RandGenerator,
+    pub ship: Ship,
+    pub enemies: EnemiesSet,
+    pub bullets: BulletsSet,
+    pub buildings: BuildingsSet,
+    pub score: Score,
+    pub waves: Waves,
+    pub level: Level,
+    // masques de collision des planches qui en demandent
+    masks: MaskLibrary,
+    pub width: f32,
+    pub height: f32,
+    // grilles de présélection des collisions, refaites à chaque pas
+    enemies_grid: SpatialGrid,
+    bullets_grid: SpatialGrid,
+    candidates: Vec<usize>,
+    // nombre de tests de collision précis faits au dernier pas
+    pub collision_tests: usize,
+    // scène de test : nombre d'ennemis et de balles maintenus à l'écran (0 = partie normale)
+    pub stress: usize,
+    // événements du pas en cours
+    events: EventBus,
+}
+
+impl World {
+    // le score vient de main.rs : le monde ne lit pas le stockage
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: f32,
+        height: f32,
+        seed: u64,
+        level: Level,
+        archetypes: Archetypes,
+        waves: WaveList,
+        masks: MaskLibrary,
+        score: Score,
+    ) -> World {
+        let rng = rand::RandGenerator::new();
+        rng.srand(seed);
+        World {
+            seed,
+            rng,
+            ship: Ship::new(width, height),
+            enemies: EnemiesSet::new(archetypes, masks.clone()),
+            bullets: BulletsSet::new(),
+            buildings: BuildingsSet::new(),
+            score,
+            waves: Waves::new(waves),
+            level,
+            masks,
+            width,
+            height,
+            enemies_grid: SpatialGrid::new(GRID_CELL_SIZE),
+            bullets_grid: SpatialGrid::new(GRID_CELL_SIZE),
+            candidates: vec![],
+            collision_tests: 0,
+            stress: 0,
+            events: EventBus::new(),
+        }
+    }
+
+    // taille de la zone de jeu, fixée au début de chaque partie (l'affichage est mis à l'échelle)
+    pub fn set_bounds(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    // nouvelle partie : une même graine et une même difficulté donnent toujours la même suite d'ennemis
+    pub fn reset(&mut self, seed: u64, difficulty: Difficulty) {
+        self.seed = seed;
+        self.rng.srand(seed);
+        self.enemies.clear();
+        self.bullets.clear();
+        self.buildings.reset(self.height, &self.level, &self.masks);
+        self.ship.reset(self.width, self.height);
+        self.score.reset();
+        self.waves.reset(difficulty);
+    }
+
+    // menace entre 0 et 1, pour la musique : ennemis à l'écran et bâtiments détruits
+    pub fn threat(&self) -> f32 {
+        let enemies = self.enemies.enemies.len() as f32 / THREAT_ENEMIES;
+        let buildings = &self.buildings.buildings;
+        let destroyed = buildings
+            .iter()
+            .filter(|building| building.shape.collided)
+            .count();
+        let lost = destroyed as f32 / buildings.len().max(1) as f32;
+        (enemies.min(1.0) * 0.6 + lost * 0.4).min(1.0)
+    }
+
+    // données modifiées en cours de partie (rechargement à chaud) : la partie continue
+    pub fn reload(&mut self, level: Level, archetypes: Archetypes, waves: WaveList) {
+        self.enemies.reload(archetypes);
+        self.waves.reload(waves);
+        self.level = level;
+    }
+
+    // ville d'une autre partie (relecture) ; les bâtiments sont refaits par reset
+    pub fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+
+    // masques recalculés après un rechargement ; les bâtiments sont refaits d'après le niveau
+    pub fn set_masks(&mut self, masks: MaskLibrary) {
+        self.enemies.set_masks(masks.clone());
+        self.buildings.reload(self.height, &self.level, &masks);
+        self.masks = masks;
+    }
+
+    // mémorise les positions avant un pas, pour l'interpolation de l'affichage
+    fn save_positions(&mut self) {
+        self.ship.ship.save_position();
+        for shape in self
+            .enemies
+            .enemies
+            .iter_mut()
+            .chain(self.bullets.bullets.iter_mut())
+        {
+            shape.save_position();
+        }
+    }
+
+    // scène de test : on complète les ennemis et les balles, placés au hasard sur l'écran
+    fn fill_stress(&mut self) {
+        while self.enemies.enemies.len() < self.stress {
+            let kind = self.rng.gen_range(0, self.enemies.archetypes.list.len());
+            self.enemies.spawn(kind, 1.0, self.width, &self.rng);
+            if let Some(enemy) = self.enemies.enemies.last_mut() {
+                enemy.y = self.rng.gen_range(0.0, self.height);
+                enemy.save_position();
+            }
+        }
+        while self.bullets.bullets.len() < self.stress {
+            // tir vers un point au hasard, puis balle déplacée n'importe où
+            let target = vec2(
+                self.rng.gen_range(0.0, self.width),
+                self.rng.gen_range(0.0, self.height),
+            );
+            let mut bullet = self.ship.shoot(Some(target));
+            bullet.x = self.rng.gen_range(0.0, self.width);
+            bullet.y = self.rng.gen_range(0.0, self.height);
+            bullet.save_position();
+            self.bullets.push(bullet);
+        }
+    }
+
+    // avance la simulation de delta_time secondes
+    pub fn step(&mut self, delta_time: f32, input: &InputState) -> Vec<GameEvent> {
+        self.save_positions();
+
+        self.ship.update(delta_time, input, self.width, self.height);
+        self.bullets.update(delta_time, self.width, self.height);
+        if let Some(summary) = self.waves.update(
+            delta_time,
+            &mut self.enemies,
+            self.width,
+            &self.rng,
+            self.score.value(),
+        ) {
+            self.events.emit(GameEvent::WaveCleared(summary));
+        }
+        self.enemies.update(delta_time, self.width, self.height);
+        self.buildings.update(delta_time);
+
+        if self.stress > 0 {
+            self.fill_stress();
+        }
+
+        if input.shoot && self.ship.nb_ammo > -1 {
+            self.bullets.push(self.ship.shoot(input.aim));
+            self.ship.nb_ammo -= 1;
+            self.events.emit(GameEvent::ShotFired {
+                x: self.ship.ship.x,
+            });
+            if self.ship.nb_ammo == -1 {
+                self.events.emit(GameEvent::AmmoEmpty);
+            }
+        }
+
+        self.collisions();
+
+        // le score et les vagues suivent les événements du pas
+        self.events
+            .dispatch(&mut [&mut self.score, &mut self.waves]);
+
+        // la scène de test ne se termine jamais
+        if self.stress > 0 {
+            return self.events.take();
+        }
+
+        //Toutes les vagues terminées ?
+        if self.waves.is_finished() {
+            self.events.emit(GameEvent::Won);
+        } else if self.ship.ship.life < 0 || self.buildings.all_destroyed() {
+            //Plus de vie ou tous les batiments détruits ?
+            self.events.emit(GameEvent::GameOver);
+        }
+        self.events.take()
+    }
+
+    // collisions présélectionnées par les grilles ; leurs conséquences partent en événements
+    fn collisions(&mut self) {
+        // points rapportés par chaque type d'ennemi
+        let values: Vec<u32> = self
+            .enemies
+            .archetypes
+            .list
+            .iter()
+            .map(|archetype| archetype.def.score)
+            .collect();
+
+        self.enemies_grid
+            .rebuild(&self.enemies.enemies, self.width, self.height);
+        self.bullets_grid
+            .rebuild(&self.bullets.bullets, self.width, self.height);
+        self.collision_tests = 0;
+
+        // Collision avec une balle
+        for enemy in self.enemies.enemies.iter_mut() {
+            self.bullets_grid
+                .query(&enemy.bounds(), &mut self.candidates);
+            self.collision_tests += self.candidates.len();
+            for &index in &self.candidates {
+                let bullet = &mut self.bullets.bullets[index];
+                // une balle ne touche qu'une seule fois
+                if bullet.collided || !bullet.collides_with(enemy) {
+                    continue;
+                }
+                bullet.collided = true;
+                // un ennemi déjà détruit pendant ce pas ne compte plus
+                if !enemy.collided {
+                    enemy.life -= 1;
+                    if enemy.life <= 0 {
+                        enemy.collided = true;
+                        self.events.emit(GameEvent::EnemyDestroyed {
+                            enemy: enemy.clone(),
+                            points: values[enemy.kind],
+                        });
+                    }
+                }
+            }
+        }
+
+        // Collision avec un bâtiment
+        for building in self.buildings.buildings.iter_mut() {
+            let shape = &mut building.shape;
+            self.enemies_grid
+                .query(&shape.bounds(), &mut self.candidates);
+            self.collision_tests += self.candidates.len();
+            for &index in &self.candidates {
+                let enemy = &mut self.enemies.enemies[index];
+                // un ennemi détruit pendant ce pas ne touche plus rien
+                if enemy.collided || !enemy.collides_with(shape) {
+                    continue;
+                }
+                enemy.collided = true;
+                self.events.emit(GameEvent::BuildingDamaged {
+                    enemy: enemy.clone(),
+                });
+                if shape.life > 0 {
+                    shape.life -= 1;
+                    if shape.life == 0 {
+                        shape.collided = true; // batiment touché
+                        self.events.emit(GameEvent::BuildingDestroyed {
+                            name: building.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // collision avec le vaisseau
+        let ship = &mut self.ship.ship;
+        self.enemies_grid
+            .query(&ship.bounds(), &mut self.candidates);
+        self.collision_tests += self.candidates.len();
+        for &index in &self.candidates {
+            let enemy = &mut self.enemies.enemies[index];
+            if enemy.collided || !enemy.collides_with(ship) {
+                continue;
+            }
+            enemy.collided = true;
+            ship.life -= 1;
+            self.events.emit(GameEvent::ShipHit {
+                lives: ship.life,
+                enemy: enemy.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_world(seed: u64) -> World {
+        let archetypes = Archetypes::default_archetypes();
+        let waves = WaveList::default_waves(&archetypes);
+        let mut world = World::new(
+            800.0,
+            800.0,
+            seed,
+            Level::default_city(),
+            archetypes,
+            waves,
+            MaskLibrary::new(),
+            Score::new(0),
+        );
+        world.reset(seed, Difficulty::Normal);
+        world
+    }
+
+    fn enemy_positions(world: &World) -> Vec<(f32, f32)> {
+        world.enemies.enemies.iter().map(|e| (e.x, e.y)).collect()
+    }
+
+    // le vaisseau va et vient en tirant, pour faire réagir la partie aux commandes
+    fn input(step: usize) -> InputState {
+        InputState {
+            movement: vec2(if step % 240 < 120 { 1.0 } else { -1.0 }, 0.0),
+            shoot: step.is_multiple_of(30),
+            aim: None,
+            pause: false,
+        }
+    }
+
+    #[test]
+    fn same_seed_and_input_give_the_same_enemies() {
+        let mut first = new_world(1234);
+        let mut second = new_world(1234);
+        let mut seen = 0;
+        for step in 0..2400 {
+            first.step(1.0 / 120.0, &input(step));
+            second.step(1.0 / 120.0, &input(step));
+            assert_eq!(enemy_positions(&first), enemy_positions(&second));
+            seen = seen.max(first.enemies.enemies.len());
+        }
+        assert!(seen > 0);
+        assert_eq!(first.score.value(), second.score.value());
+    }
+
+    #[test]
+    fn another_seed_gives_other_enemies() {
+        let mut first = new_world(1);
+        let mut second = new_world(2);
+        let (mut first_run, mut second_run) = (vec![], vec![]);
+        for step in 0..2400 {
+            first.step(1.0 / 120.0, &input(step));
+            second.step(1.0 / 120.0, &input(step));
+            first_run.push(enemy_positions(&first));
+            second_run.push(enemy_positions(&second));
+        }
+        assert_ne!(first_run, second_run);
+    }
+}
@@ -5,5 +5,6 @@ copy index.html .\www\
 copy assets\*.* .\www\assets
 copy quad-storage.js .\www\
 copy sapp_jsutils.js .\www\
+copy gamepad.js .\www\
 cd .\www
 python -m http.server 8000